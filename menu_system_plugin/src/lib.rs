@@ -1,14 +1,55 @@
-pub fn add(left: usize, right: usize) -> usize {
-    left + right
+pub mod menu;
+pub mod navigation;
+pub mod render;
+pub mod sprite;
+pub mod ui;
+
+use bevy::prelude::*;
+use bevy_common_assets::json::JsonAssetPlugin;
+use crate::menu::MenuDefinition;
+use crate::navigation::*;
+use crate::render::{DrawnMenu, MenuRenderer, render_menu};
+use crate::ui::UiMenuRenderer;
+
+// Loads a menu tree from a `.menu.json` asset, drives it from the keyboard and draws it with R. The menu starts closed
+pub struct MenuSystemPlugin<R> {
+    pub path: String,
+    pub renderer: R
+}
+
+impl MenuSystemPlugin<UiMenuRenderer> {
+    pub fn new(path: &str) -> Self {
+        MenuSystemPlugin {
+            path: path.to_string(),
+            renderer: UiMenuRenderer::default()
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<R> MenuSystemPlugin<R> {
+    pub fn with_renderer<N: MenuRenderer + Clone>(self, renderer: N) -> MenuSystemPlugin<N> {
+        MenuSystemPlugin {
+            path: self.path,
+            renderer
+        }
+    }
+}
+
+impl<R: MenuRenderer + Clone> Plugin for MenuSystemPlugin<R> {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(JsonAssetPlugin::<MenuDefinition>::new(&["menu.json"]))
+            .add_event::<MenuSelectedEvent>()
+            .add_event::<MenuCancelledEvent>()
+            .insert_resource(MenuState::default())
+            .insert_resource(self.renderer.clone())
+            .init_resource::<DrawnMenu>()
+            .add_systems(Update, navigate_menu.in_set(MenuSet).run_if(menu_open))
+            .add_systems(PostUpdate, render_menu::<R>.run_if(resource_changed::<MenuState>()));
+    }
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    // The asset server only exists once every plugin has been built
+    fn finish(&self, app: &mut App) {
+        let definition = app.world.resource::<AssetServer>().load(self.path.clone());
+        app.world.resource_mut::<MenuState>().definition = definition;
     }
 }
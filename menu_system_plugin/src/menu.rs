@@ -0,0 +1,40 @@
+use bevy::utils::HashMap;
+
+// A whole menu tree, e.g. `{ "pages": { "main": { "title": "Samurai Warriors", "items": [...] } } }`
+#[derive(serde::Deserialize, bevy::asset::Asset, bevy::reflect::TypePath, Clone, Debug)]
+pub struct MenuDefinition {
+    pub pages: HashMap<String, MenuPage>
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct MenuPage {
+    #[serde(default)]
+    pub title: String,
+    pub items: Vec<MenuItem>,
+    // Shown under the items whenever the game hasn't put a note of its own there
+    #[serde(default)]
+    pub note: Option<String>
+}
+
+// `{ "label": "New Run", "action": "new_run" }` or `{ "label": "Options", "submenu": "options" }`
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct MenuItem {
+    pub label: String,
+    #[serde(flatten)]
+    pub kind: MenuItemKind
+}
+
+#[derive(serde::Deserialize, Eq, PartialEq, Clone, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum MenuItemKind {
+    // Sent out in a MenuSelectedEvent for the game to act on
+    Action(String),
+    // Opens another page, going back returns to this one
+    Submenu(String)
+}
+
+impl MenuDefinition {
+    pub fn page(&self, page: &str) -> Option<&MenuPage> {
+        self.pages.get(page)
+    }
+}
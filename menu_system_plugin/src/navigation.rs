@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use crate::menu::{MenuDefinition, MenuItem, MenuItemKind};
+
+const UP_KEYS: [KeyCode; 2] = [KeyCode::Up, KeyCode::W];
+const DOWN_KEYS: [KeyCode; 2] = [KeyCode::Down, KeyCode::S];
+const SELECT_KEYS: [KeyCode; 2] = [KeyCode::Return, KeyCode::Space];
+const BACK_KEYS: [KeyCode; 2] = [KeyCode::Escape, KeyCode::Back];
+
+// Keyboard navigation runs in here, so games can order their own menu handling around it
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MenuSet;
+
+// Where the player is in the menu, the menu is closed while no page is open
+#[derive(Resource, Default, Debug)]
+pub struct MenuState {
+    pub definition: Handle<MenuDefinition>,
+    // Every page opened on the way to the one on screen, which is the last
+    pub pages: Vec<String>,
+    pub selected: usize,
+    // Actions that are shown but can't be picked right now, e.g. continuing without a saved run
+    pub disabled: HashSet<String>,
+    // Shown in place of the items while the game takes over the keyboard, e.g. to type something in
+    pub prompt: Option<String>,
+    // Extra text shown under the items
+    pub note: Option<String>,
+    // Shown beside the item with the same action, e.g. the current setting of an option. Kept while the menu is closed
+    pub values: HashMap<String, String>
+}
+
+// An action item was picked, the game decides what it does
+#[derive(Event, Eq, PartialEq, Clone, Debug)]
+pub struct MenuSelectedEvent {
+    pub action: String,
+    pub page: String
+}
+
+// Back was pressed with no page left to go back to
+#[derive(Event, Eq, PartialEq, Clone, Debug)]
+pub struct MenuCancelledEvent {
+    pub page: String
+}
+
+impl MenuState {
+    pub fn is_open(&self) -> bool {
+        !self.pages.is_empty()
+    }
+
+    pub fn current_page(&self) -> Option<&str> {
+        self.pages.last().map(String::as_str)
+    }
+
+    // Opens a page on its own, whatever was open before is forgotten
+    pub fn open(&mut self, page: &str, definition: &MenuDefinition) {
+        self.pages = vec![page.to_string()];
+        self.selected = self.first_enabled(definition);
+        self.prompt = None;
+        self.note = None;
+    }
+
+    pub fn close(&mut self) {
+        self.pages.clear();
+        self.selected = 0;
+        self.prompt = None;
+        self.note = None;
+    }
+
+    pub fn value(&self, item: &MenuItem) -> Option<&String> {
+        match &item.kind {
+            MenuItemKind::Action(action) => self.values.get(action),
+            MenuItemKind::Submenu(_) => None
+        }
+    }
+
+    pub fn is_enabled(&self, item: &MenuItem) -> bool {
+        match &item.kind {
+            MenuItemKind::Action(action) => !self.disabled.contains(action),
+            MenuItemKind::Submenu(_) => true
+        }
+    }
+
+    fn items<'a>(&self, definition: &'a MenuDefinition) -> &'a [MenuItem] {
+        self.current_page()
+            .and_then(|page| definition.page(page))
+            .map_or(&[], |page| page.items.as_slice())
+    }
+
+    fn first_enabled(&self, definition: &MenuDefinition) -> usize {
+        self.items(definition).iter()
+            .position(|item| self.is_enabled(item))
+            .unwrap_or(0)
+    }
+
+    // Steps through the items, wrapping around either end and skipping anything disabled
+    pub fn move_selection(&mut self, definition: &MenuDefinition, step: isize) {
+        let items = self.items(definition);
+        let len = items.len() as isize;
+        let mut selected = self.selected as isize;
+        for _ in 0..len {
+            selected = (selected + step).rem_euclid(len);
+            if self.is_enabled(&items[selected as usize]) {
+                self.selected = selected as usize;
+                return;
+            }
+        }
+    }
+
+    // Submenus are opened here, actions are handed back to be sent out
+    pub fn select(&mut self, definition: &MenuDefinition) -> Option<String> {
+        let item = self.items(definition).get(self.selected)?;
+        if !self.is_enabled(item) {
+            return None;
+        }
+        match &item.kind {
+            MenuItemKind::Action(action) => Some(action.clone()),
+            MenuItemKind::Submenu(page) => {
+                let page = page.clone();
+                self.pages.push(page);
+                self.selected = self.first_enabled(definition);
+                None
+            }
+        }
+    }
+
+    // Returns to the page before with the item that led here selected, false if there's nothing to go back to
+    pub fn back(&mut self, definition: &MenuDefinition) -> bool {
+        if self.pages.len() < 2 {
+            return false;
+        }
+        let left_page = self.pages.pop().map(MenuItemKind::Submenu);
+        self.selected = self.items(definition).iter()
+            .position(|item| Some(&item.kind) == left_page.as_ref())
+            .unwrap_or_else(|| self.first_enabled(definition));
+        true
+    }
+}
+
+pub fn menu_open(menu_state: Res<MenuState>) -> bool {
+    menu_state.is_open()
+}
+
+pub(crate) fn navigate_menu(
+    keys: Res<Input<KeyCode>>,
+    definitions: Res<Assets<MenuDefinition>>,
+    mut menu_state: ResMut<MenuState>,
+    mut ev_selected: EventWriter<MenuSelectedEvent>,
+    mut ev_cancelled: EventWriter<MenuCancelledEvent>
+) {
+    let Some(definition) = definitions.get(&menu_state.definition) else {
+        return;
+    };
+    let Some(page) = menu_state.current_page().map(str::to_string) else {
+        return;
+    };
+    if menu_state.prompt.is_some() {
+        return;
+    }
+
+    if keys.any_just_pressed(UP_KEYS) {
+        menu_state.move_selection(definition, -1);
+    }
+    if keys.any_just_pressed(DOWN_KEYS) {
+        menu_state.move_selection(definition, 1);
+    }
+    if keys.any_just_pressed(SELECT_KEYS) {
+        if let Some(action) = menu_state.select(definition) {
+            ev_selected.send(MenuSelectedEvent { action, page });
+        }
+    } else if keys.any_just_pressed(BACK_KEYS) && !menu_state.back(definition) {
+        ev_cancelled.send(MenuCancelledEvent { page });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MENU: &str = r#"{
+        "pages": {
+            "main": { "title": "Samurai Warriors", "items": [
+                { "label": "Continue", "action": "continue" },
+                { "label": "New Run", "action": "new_run" },
+                { "label": "Options", "submenu": "options" },
+                { "label": "Quit", "action": "quit" }
+            ] },
+            "options": { "items": [
+                { "label": "Controls", "action": "controls" },
+                { "label": "Sound", "action": "sound" }
+            ] }
+        }
+    }"#;
+
+    fn definition() -> MenuDefinition {
+        serde_json::from_str(MENU).unwrap()
+    }
+
+    #[test]
+    fn selection_wraps_and_skips_disabled_items() {
+        let definition = definition();
+        let mut menu_state = MenuState::default();
+        menu_state.disabled.insert("continue".to_string());
+        menu_state.open("main", &definition);
+        assert_eq!(menu_state.selected, 1);
+
+        menu_state.move_selection(&definition, -1);
+        assert_eq!(menu_state.selected, 3);
+        menu_state.move_selection(&definition, 1);
+        assert_eq!(menu_state.selected, 1);
+        assert_eq!(menu_state.select(&definition), Some("new_run".to_string()));
+    }
+
+    #[test]
+    fn submenus_open_and_go_back() {
+        let definition = definition();
+        let mut menu_state = MenuState::default();
+        menu_state.open("main", &definition);
+        menu_state.selected = 2;
+
+        assert_eq!(menu_state.select(&definition), None);
+        assert_eq!(menu_state.current_page(), Some("options"));
+        menu_state.move_selection(&definition, 1);
+        assert_eq!(menu_state.select(&definition), Some("sound".to_string()));
+
+        assert!(menu_state.back(&definition));
+        assert_eq!(menu_state.current_page(), Some("main"));
+        assert_eq!(menu_state.selected, 2);
+        assert!(!menu_state.back(&definition));
+        assert!(menu_state.is_open());
+    }
+}
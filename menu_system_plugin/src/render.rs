@@ -0,0 +1,203 @@
+use bevy::ecs::system::{StaticSystemParam, SystemParam, SystemParamItem};
+use bevy::prelude::*;
+use crate::menu::MenuDefinition;
+use crate::navigation::MenuState;
+
+// Everything a renderer needs to draw the open page, worked out from the menu state
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct MenuView {
+    pub title: String,
+    pub items: Vec<MenuLine>,
+    pub prompt: Option<String>,
+    pub note: Option<String>
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct MenuLine {
+    pub label: String,
+    pub value: Option<String>,
+    pub selected: bool,
+    pub enabled: bool
+}
+
+impl MenuView {
+    // None while the menu is closed, or the open page isn't in the definition
+    pub fn new(menu_state: &MenuState, definition: &MenuDefinition) -> Option<MenuView> {
+        let page = definition.page(menu_state.current_page()?)?;
+        Some(MenuView {
+            title: page.title.clone(),
+            items: page.items.iter().enumerate().map(|(idx, item)| MenuLine {
+                label: item.label.clone(),
+                value: menu_state.value(item).cloned(),
+                selected: idx == menu_state.selected,
+                enabled: menu_state.is_enabled(item)
+            }).collect(),
+            prompt: menu_state.prompt.clone(),
+            note: menu_state.note.clone().or_else(|| page.note.clone())
+        })
+    }
+
+    // Every line of text in the order it's shown, for renderers that lay the menu out line by line
+    pub fn rows(&self) -> Vec<MenuRow> {
+        let mut rows = vec![MenuRow::new(&self.title, RowStyle::Title), MenuRow::new("", RowStyle::Text)];
+        match &self.prompt {
+            Some(prompt) => rows.push(MenuRow::new(prompt, RowStyle::Text)),
+            None => rows.extend(self.items.iter().map(|item| {
+                let style = match (item.enabled, item.selected) {
+                    (false, _) => RowStyle::Disabled,
+                    (true, true) => RowStyle::Selected,
+                    (true, false) => RowStyle::Item
+                };
+                match &item.value {
+                    Some(value) => MenuRow::new(&format!("{}: {}", item.label, value), style),
+                    None => MenuRow::new(&item.label, style)
+                }
+            }))
+        }
+        if let Some(note) = &self.note {
+            rows.push(MenuRow::new("", RowStyle::Text));
+            rows.push(MenuRow::new(note, RowStyle::Text));
+        }
+        rows
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct MenuRow {
+    pub text: String,
+    pub style: RowStyle
+}
+
+impl MenuRow {
+    fn new(text: &str, style: RowStyle) -> MenuRow {
+        MenuRow {
+            text: text.to_string(),
+            style
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum RowStyle {
+    Title,
+    Text,
+    Item,
+    Selected,
+    Disabled
+}
+
+// The text colours shared by the renderers
+#[derive(Clone, Copy, Debug)]
+pub struct MenuPalette {
+    pub text: Color,
+    pub selected: Color,
+    pub disabled: Color
+}
+
+impl Default for MenuPalette {
+    fn default() -> Self {
+        MenuPalette {
+            text: Color::WHITE,
+            selected: Color::YELLOW,
+            disabled: Color::GRAY
+        }
+    }
+}
+
+impl MenuPalette {
+    pub fn color(&self, style: RowStyle) -> Color {
+        match style {
+            RowStyle::Selected => self.selected,
+            RowStyle::Disabled => self.disabled,
+            RowStyle::Title | RowStyle::Text | RowStyle::Item => self.text
+        }
+    }
+}
+
+// Draws the menu, the menu is thrown away and drawn again from scratch whenever it changes
+pub trait MenuRenderer: Resource {
+    // Anything else the renderer needs from the world, e.g. the textures it draws with
+    type Param: SystemParam + 'static;
+
+    // Returns the entity at the root of what was spawned, it's despawned recursively to clear the menu
+    fn draw(&self, commands: &mut Commands, view: &MenuView, param: &mut SystemParamItem<'_, '_, Self::Param>) -> Entity;
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct DrawnMenu(Option<Entity>);
+
+pub(crate) fn render_menu<R: MenuRenderer>(
+    mut commands: Commands,
+    renderer: Res<R>,
+    mut param: StaticSystemParam<R::Param>,
+    menu_state: Res<MenuState>,
+    definitions: Res<Assets<MenuDefinition>>,
+    mut drawn_menu: ResMut<DrawnMenu>
+) {
+    if let Some(entity) = drawn_menu.0.take() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let view = definitions.get(&menu_state.definition)
+        .and_then(|definition| MenuView::new(&menu_state, definition));
+    if let Some(view) = view {
+        drawn_menu.0 = Some(renderer.draw(&mut commands, &view, &mut param));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MENU: &str = r#"{
+        "pages": {
+            "main": { "title": "Samurai Warriors", "items": [
+                { "label": "Continue", "action": "continue" },
+                { "label": "New Run", "action": "new_run" }
+            ] },
+            "controls": { "title": "Controls", "note": "Escape goes back", "items": [
+                { "label": "North", "action": "bind:North" }
+            ] }
+        }
+    }"#;
+
+    #[test]
+    fn view_follows_the_menu_state() {
+        let definition: MenuDefinition = serde_json::from_str(MENU).unwrap();
+        let mut menu_state = MenuState::default();
+        assert_eq!(MenuView::new(&menu_state, &definition), None);
+
+        menu_state.disabled.insert("continue".to_string());
+        menu_state.open("main", &definition);
+        menu_state.note = Some("Seed: 42".to_string());
+        menu_state.values.insert("new_run".to_string(), "Depth 1".to_string());
+        let view = MenuView::new(&menu_state, &definition).unwrap();
+        assert_eq!(view.items, vec![
+            MenuLine { label: "Continue".to_string(), value: None, selected: false, enabled: false },
+            MenuLine { label: "New Run".to_string(), value: Some("Depth 1".to_string()), selected: true, enabled: true }
+        ]);
+        assert_eq!(view.rows(), vec![
+            MenuRow::new("Samurai Warriors", RowStyle::Title),
+            MenuRow::new("", RowStyle::Text),
+            MenuRow::new("Continue", RowStyle::Disabled),
+            MenuRow::new("New Run: Depth 1", RowStyle::Selected),
+            MenuRow::new("", RowStyle::Text),
+            MenuRow::new("Seed: 42", RowStyle::Text)
+        ]);
+
+        menu_state.prompt = Some("Type a seed".to_string());
+        let rows = MenuView::new(&menu_state, &definition).unwrap().rows();
+        assert_eq!(rows[2], MenuRow::new("Type a seed", RowStyle::Text));
+        assert_eq!(rows.len(), 5);
+    }
+
+    #[test]
+    fn page_note_gives_way_to_the_game_note() {
+        let definition: MenuDefinition = serde_json::from_str(MENU).unwrap();
+        let mut menu_state = MenuState::default();
+        menu_state.open("controls", &definition);
+        assert_eq!(MenuView::new(&menu_state, &definition).unwrap().note, Some("Escape goes back".to_string()));
+
+        menu_state.note = Some("W is already used by North".to_string());
+        assert_eq!(MenuView::new(&menu_state, &definition).unwrap().note, Some("W is already used by North".to_string()));
+    }
+}
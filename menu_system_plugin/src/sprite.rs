@@ -0,0 +1,118 @@
+use std::hash::Hash;
+use std::marker::PhantomData;
+use asset_loading_plugin::loader::{Loadable, LoadedData};
+use bevy::ecs::system::lifetimeless::SRes;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use crate::render::{MenuPalette, MenuRenderer, MenuView, RowStyle};
+
+// Roughly how wide a character of the default font is, compared to its size
+const GLYPH_WIDTH: f32 = 0.6;
+
+// Whatever the game keeps in LoadedData that has an image the menu can be drawn with
+pub trait MenuTexture: Loadable {
+    fn image(&self) -> Handle<Image>;
+}
+
+// Draws the menu in the world out of the game's own tiles, centred on the origin above the map.
+// A is the key textures are loaded under, T the data holding each texture
+#[derive(Resource, Clone, Debug)]
+pub struct SpriteMenuRenderer<A, T> {
+    // Tiled behind the whole menu
+    pub panel: A,
+    // Drawn beside the selected item
+    pub cursor: A,
+    pub tile_size: f32,
+    pub font_size: f32,
+    pub z: f32,
+    pub palette: MenuPalette,
+    _texture: PhantomData<T>
+}
+
+impl<A, T> SpriteMenuRenderer<A, T> {
+    pub fn new(panel: A, cursor: A, tile_size: f32) -> Self {
+        SpriteMenuRenderer {
+            panel,
+            cursor,
+            tile_size,
+            font_size: tile_size,
+            z: 10.0,
+            palette: MenuPalette::default(),
+            _texture: PhantomData
+        }
+    }
+}
+
+impl<A: Eq + Hash, T: MenuTexture> SpriteMenuRenderer<A, T> {
+    // The first texture loaded under the key, a missing one is simply left out of the menu
+    fn texture(&self, loaded_data: &LoadedData<A>, key: &A) -> Option<Handle<Image>> {
+        loaded_data.asset_data.get(key)?
+            .first()?
+            .get_requested_type::<T>()
+            .map(MenuTexture::image)
+    }
+
+    fn tile(&self, texture: Handle<Image>, position: Vec3) -> SpriteBundle {
+        SpriteBundle {
+            texture,
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(self.tile_size)),
+                ..default()
+            },
+            transform: Transform::from_translation(position),
+            ..default()
+        }
+    }
+}
+
+impl<A, T> MenuRenderer for SpriteMenuRenderer<A, T>
+    where A: Eq + Hash + Send + Sync + 'static, T: MenuTexture {
+    type Param = SRes<LoadedData<A>>;
+
+    fn draw(&self, commands: &mut Commands, view: &MenuView, loaded_data: &mut Res<LoadedData<A>>) -> Entity {
+        let rows = view.rows();
+        let longest_row = rows.iter().map(|row| row.text.chars().count()).max().unwrap_or(0);
+        let text_columns = (longest_row as f32 * self.font_size * GLYPH_WIDTH / self.tile_size).ceil() as usize;
+        // One row of tiles per line of text, with a border all round and a column for the cursor
+        let columns = text_columns + 3;
+        let tile_rows = rows.len() + 2;
+        let left = -(columns as f32) * self.tile_size / 2.0;
+        let top = tile_rows as f32 * self.tile_size / 2.0;
+        let tile_center = |column: usize, row: usize, z: f32| Vec3::new(
+            left + (column as f32 + 0.5) * self.tile_size,
+            top - (row as f32 + 0.5) * self.tile_size,
+            z
+        );
+
+        let panel = self.texture(loaded_data, &self.panel);
+        let cursor = self.texture(loaded_data, &self.cursor);
+        commands.spawn(SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, self.z))).with_children(|parent| {
+            if let Some(panel) = panel {
+                for column in 0..columns {
+                    for row in 0..tile_rows {
+                        parent.spawn(self.tile(panel.clone(), tile_center(column, row, 0.0)));
+                    }
+                }
+            }
+
+            for (idx, row) in rows.iter().enumerate() {
+                let row_center = tile_center(2, idx + 1, 2.0);
+                if row.style == RowStyle::Selected {
+                    if let Some(cursor) = &cursor {
+                        parent.spawn(self.tile(cursor.clone(), tile_center(1, idx + 1, 1.0)));
+                    }
+                }
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(row.text.clone(), TextStyle {
+                        font_size: self.font_size,
+                        color: self.palette.color(row.style),
+                        ..default()
+                    }),
+                    text_anchor: Anchor::CenterLeft,
+                    transform: Transform::from_translation(row_center - Vec3::X * self.tile_size / 2.0),
+                    ..default()
+                });
+            }
+        }).id()
+    }
+}
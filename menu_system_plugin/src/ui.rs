@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+use crate::render::{MenuPalette, MenuRenderer, MenuView, RowStyle};
+
+// Plain Bevy UI text in the top left of the window, the default renderer
+#[derive(Resource, Clone, Debug)]
+pub struct UiMenuRenderer {
+    pub font_size: f32,
+    pub palette: MenuPalette
+}
+
+impl Default for UiMenuRenderer {
+    fn default() -> Self {
+        UiMenuRenderer {
+            font_size: 32.0,
+            palette: MenuPalette::default()
+        }
+    }
+}
+
+impl MenuRenderer for UiMenuRenderer {
+    type Param = ();
+
+    fn draw(&self, commands: &mut Commands, view: &MenuView, _param: &mut ()) -> Entity {
+        let sections = view.rows().into_iter().map(|row| {
+            let marker = if row.style == RowStyle::Selected { "> " } else if row.style == RowStyle::Title { "" } else { "  " };
+            TextSection::new(format!("{}{}\n", marker, row.text), TextStyle {
+                font_size: self.font_size,
+                color: self.palette.color(row.style),
+                ..default()
+            })
+        });
+
+        commands.spawn(TextBundle::from_sections(sections).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            left: Val::Px(40.0),
+            ..default()
+        })).id()
+    }
+}
@@ -0,0 +1,177 @@
+use bevy::prelude::{Component, EventReader, EventWriter, Query, Res, ResMut, With, Without};
+use bevy_ecs_tilemap::prelude::{TilePos, TileStorage};
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::GlobalEntropy;
+use rand_core::RngCore;
+use worldgen::rand_range;
+use crate::lifeform::{Direction, Enemy, Lifeform, MoveEvent, PlayerCharacter};
+use crate::pathfinding::{DijkstraMap, PathGrid, Point, find_path};
+use crate::tile_data::TerrainData;
+use crate::turn::ActorReadyEvent;
+use crate::world::{Dungeon, LifeformLayer, TerrainLayer};
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub enum AiState {
+    Idle,
+    Wander,
+    Chase,
+    Flee
+}
+
+#[derive(Component, Clone)]
+pub struct Ai {
+    pub state: AiState,
+    // Where the enemy spawned, wandering never strays further than wander_radius from here
+    pub home: TilePos,
+    pub wander_radius: u32,
+    pub sight_range: u32,
+    // Flee once health drops to or below this percentage of max health
+    pub flee_percent: u32
+}
+
+impl Ai {
+    pub fn new(lifeform: &Lifeform) -> Ai {
+        Ai {
+            state: AiState::Idle,
+            home: lifeform.position,
+            wander_radius: 3,
+            sight_range: 8,
+            flee_percent: 25
+        }
+    }
+
+    fn should_flee(&self, lifeform: &Lifeform) -> bool {
+        // Widened so huge health values from data files can't overflow
+        lifeform.health as u64 * 100 <= lifeform.max_health as u64 * self.flee_percent as u64
+    }
+}
+
+// Steps apart in the open, diagonal steps count the same as straight ones so diagonal neighbors are 1 apart
+fn distance(from: &TilePos, to: &TilePos) -> u32 {
+    from.x.abs_diff(to.x).max(from.y.abs_diff(to.y))
+}
+
+fn has_line_of_sight(from: &TilePos, to: &TilePos, grid: &PathGrid) -> bool {
+    // Bresenham from one tile to the other, anything impassable in between blocks the view
+    let (mut x, mut y) = (from.x as i64, from.y as i64);
+    let (to_x, to_y) = (to.x as i64, to.y as i64);
+    let delta_x = (to_x - x).abs();
+    let delta_y = -(to_y - y).abs();
+    let step_x = if x < to_x { 1 } else { -1 };
+    let step_y = if y < to_y { 1 } else { -1 };
+    let mut error = delta_x + delta_y;
+
+    loop {
+        if x == to_x && y == to_y {
+            return true;
+        }
+        if (x != from.x as i64 || y != from.y as i64) && !grid.is_passable((x as usize, y as usize)) {
+            return false;
+        }
+        let doubled_error = 2 * error;
+        if doubled_error >= delta_y {
+            error += delta_y;
+            x += step_x;
+        }
+        if doubled_error <= delta_x {
+            error += delta_x;
+            y += step_y;
+        }
+    }
+}
+
+fn build_grid(terrain: &[Vec<TerrainData>], lifeform_storage: &TileStorage) -> PathGrid {
+    let mut grid = PathGrid::from_terrain(terrain);
+    for x in 0..lifeform_storage.size.x {
+        for y in 0..lifeform_storage.size.y {
+            grid.set_blocked((x as usize, y as usize), lifeform_storage.checked_get(&TilePos { x, y }).is_some());
+        }
+    }
+    grid
+}
+
+fn to_point(tile_pos: &TilePos) -> Point {
+    (tile_pos.x as usize, tile_pos.y as usize)
+}
+
+fn to_tile_pos(point: Point) -> TilePos {
+    TilePos { x: point.0 as u32, y: point.1 as u32 }
+}
+
+fn wander<R: RngCore>(ai: &Ai, from: &TilePos, grid: &PathGrid, rng: &mut R) -> Option<TilePos> {
+    // Chasing or fleeing can leave an enemy well away from home, it makes its way back before wandering about again
+    if distance(from, &ai.home) > ai.wander_radius {
+        return find_path(grid, to_point(from), to_point(&ai.home))?.first().copied().map(to_tile_pos);
+    }
+    let options: Vec<Point> = grid.neighbors(to_point(from)).into_iter()
+        .filter(|next| grid.is_walkable(*next) && distance(&to_tile_pos(*next), &ai.home) <= ai.wander_radius)
+        .collect();
+    if options.is_empty() {
+        return None;
+    }
+    Some(to_tile_pos(options[rand_range(0, options.len() as u32, rng) as usize]))
+}
+
+pub fn enemy_turns(
+    mut ev_actor_ready: EventReader<ActorReadyEvent>,
+    mut ev_move: EventWriter<MoveEvent>,
+    mut enemies: Query<(&TilePos, &Lifeform, &mut Ai), With<Enemy>>,
+    player: Query<&TilePos, With<PlayerCharacter>>,
+    lifeform_layer: Query<&TileStorage, (With<LifeformLayer>, Without<TerrainLayer>)>,
+    dungeon: Res<Dungeon>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>
+) {
+    let Ok(lifeform_storage) = lifeform_layer.get_single() else {
+        return;
+    };
+    if ev_actor_ready.is_empty() {
+        return;
+    }
+    let player_pos = player.get_single().ok();
+
+    // Everyone acting this turn shares the same map of distances to the player
+    let mut grid = build_grid(&dungeon.current_terrain, lifeform_storage);
+    let player_map = player_pos.map(|player_pos| DijkstraMap::new(&grid, &[to_point(player_pos)]));
+
+    for ev in ev_actor_ready.read() {
+        let Ok((tile_pos, lifeform, mut ai)) = enemies.get_mut(ev.entity) else {
+            continue;
+        };
+
+        let sees_player = match player_pos {
+            Some(player_pos) => distance(tile_pos, player_pos) <= ai.sight_range
+                && has_line_of_sight(tile_pos, player_pos, &grid),
+            None => false
+        };
+
+        ai.state = if sees_player {
+            if ai.should_flee(lifeform) { AiState::Flee } else { AiState::Chase }
+        } else {
+            match ai.state {
+                // Lost sight of the player, go back to minding our own business
+                AiState::Chase | AiState::Flee => AiState::Wander,
+                // Idle enemies occasionally get up and wander, and vice versa
+                AiState::Idle | AiState::Wander => if rand_range(0, 4, &mut *rng) == 0 { AiState::Idle } else { AiState::Wander }
+            }
+        };
+
+        let next_pos = match (ai.state, &player_map) {
+            (AiState::Chase, Some(player_map)) => player_map.step_towards(&grid, to_point(tile_pos)).map(to_tile_pos),
+            (AiState::Flee, Some(player_map)) => player_map.step_away(&grid, to_point(tile_pos)).map(to_tile_pos),
+            (AiState::Wander, _) => wander(&ai, tile_pos, &grid, &mut *rng),
+            _ => None
+        };
+
+        let Some(next_pos) = next_pos else {
+            continue;
+        };
+        if let Some(direction) = Direction::towards(tile_pos, &next_pos) {
+            ev_move.send(MoveEvent { entity: ev.entity, direction });
+            // Moves resolve later this frame, so claim the destination now to stop others walking into the same tile
+            if Some(&next_pos) != player_pos {
+                grid.set_blocked(to_point(tile_pos), false);
+                grid.set_blocked(to_point(&next_pos), true);
+            }
+        }
+    }
+}
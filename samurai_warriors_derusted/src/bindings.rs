@@ -0,0 +1,353 @@
+use bevy::input::keyboard::ScanCode;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_pkv::PkvStore;
+use leafwing_input_manager::prelude::{ActionState, InputManagerBundle, InputMap, QwertyScanCode};
+use menu_system_plugin::navigation::{MenuSelectedEvent, MenuState};
+use crate::Action;
+use crate::lifeform::Player;
+
+const BINDINGS_KEY: &str = "bindings";
+// Controls menu items are "bind:North" and so on, one for every action
+const BIND_PREFIX: &str = "bind:";
+const RESET_BINDINGS: &str = "reset_bindings";
+
+// Every action that can be rebound, in the order the controls menu lists them
+const REBINDABLE: [Action; 16] = [
+    Action::North, Action::South, Action::East, Action::West,
+    Action::NorthEast, Action::NorthWest, Action::SouthEast, Action::SouthWest,
+    Action::Skip, Action::Rest, Action::PickUp, Action::Descend, Action::Ascend, Action::Look, Action::Inventory,
+    Action::Pause
+];
+
+// Every key the controls menu can bind, anything else pressed while rebinding is turned down
+const BINDABLE_KEYS: [QwertyScanCode; 64] = [
+    QwertyScanCode::A, QwertyScanCode::B, QwertyScanCode::C, QwertyScanCode::D, QwertyScanCode::E, QwertyScanCode::F,
+    QwertyScanCode::G, QwertyScanCode::H, QwertyScanCode::I, QwertyScanCode::J, QwertyScanCode::K, QwertyScanCode::L,
+    QwertyScanCode::M, QwertyScanCode::N, QwertyScanCode::O, QwertyScanCode::P, QwertyScanCode::Q, QwertyScanCode::R,
+    QwertyScanCode::S, QwertyScanCode::T, QwertyScanCode::U, QwertyScanCode::V, QwertyScanCode::W, QwertyScanCode::X,
+    QwertyScanCode::Y, QwertyScanCode::Z,
+    QwertyScanCode::Key1, QwertyScanCode::Key2, QwertyScanCode::Key3, QwertyScanCode::Key4, QwertyScanCode::Key5,
+    QwertyScanCode::Key6, QwertyScanCode::Key7, QwertyScanCode::Key8, QwertyScanCode::Key9, QwertyScanCode::Key0,
+    QwertyScanCode::Numpad1, QwertyScanCode::Numpad2, QwertyScanCode::Numpad3, QwertyScanCode::Numpad4, QwertyScanCode::Numpad5,
+    QwertyScanCode::Numpad6, QwertyScanCode::Numpad7, QwertyScanCode::Numpad8, QwertyScanCode::Numpad9, QwertyScanCode::Numpad0,
+    QwertyScanCode::Up, QwertyScanCode::Down, QwertyScanCode::Left, QwertyScanCode::Right,
+    QwertyScanCode::Space, QwertyScanCode::Tab, QwertyScanCode::Escape, QwertyScanCode::Return,
+    QwertyScanCode::Comma, QwertyScanCode::Period, QwertyScanCode::Slash, QwertyScanCode::Semicolon, QwertyScanCode::Apostrophe,
+    QwertyScanCode::BracketLeft, QwertyScanCode::BracketRight, QwertyScanCode::Backslash, QwertyScanCode::Minus, QwertyScanCode::Equals
+];
+
+// Keys are bound by where they are on the keyboard rather than what they type, so WASD and the vi-keys keep their shape
+// on AZERTY and Dvorak. They're named after the key in that spot on a QWERTY keyboard
+#[derive(serde::Serialize, serde::Deserialize, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum Binding {
+    Key(QwertyScanCode),
+    Gamepad(GamepadButtonType)
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Gamepad(button) => write!(f, "Pad {:?}", button)
+        }
+    }
+}
+
+// What each action is bound to, the player's InputMap is built from this and it's what gets saved
+#[derive(Resource, serde::Serialize, serde::Deserialize, Eq, PartialEq, Clone, Debug)]
+pub struct Bindings {
+    pub actions: HashMap<Action, Vec<Binding>>
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let defaults = |action| match action {
+            // WASD, the arrows, the numpad and the vi-keys all move
+            Action::North => vec![Binding::Key(QwertyScanCode::W), Binding::Key(QwertyScanCode::Up), Binding::Key(QwertyScanCode::Numpad8), Binding::Key(QwertyScanCode::K), Binding::Gamepad(GamepadButtonType::DPadUp)],
+            Action::South => vec![Binding::Key(QwertyScanCode::S), Binding::Key(QwertyScanCode::Down), Binding::Key(QwertyScanCode::Numpad2), Binding::Key(QwertyScanCode::J), Binding::Gamepad(GamepadButtonType::DPadDown)],
+            Action::East => vec![Binding::Key(QwertyScanCode::D), Binding::Key(QwertyScanCode::Right), Binding::Key(QwertyScanCode::Numpad6), Binding::Key(QwertyScanCode::L), Binding::Gamepad(GamepadButtonType::DPadRight)],
+            Action::West => vec![Binding::Key(QwertyScanCode::A), Binding::Key(QwertyScanCode::Left), Binding::Key(QwertyScanCode::Numpad4), Binding::Key(QwertyScanCode::H), Binding::Gamepad(GamepadButtonType::DPadLeft)],
+            Action::NorthEast => vec![Binding::Key(QwertyScanCode::Numpad9), Binding::Key(QwertyScanCode::U)],
+            Action::NorthWest => vec![Binding::Key(QwertyScanCode::Numpad7), Binding::Key(QwertyScanCode::Y)],
+            Action::SouthEast => vec![Binding::Key(QwertyScanCode::Numpad3), Binding::Key(QwertyScanCode::N)],
+            Action::SouthWest => vec![Binding::Key(QwertyScanCode::Numpad1), Binding::Key(QwertyScanCode::B)],
+            Action::Skip => vec![Binding::Key(QwertyScanCode::Space), Binding::Key(QwertyScanCode::Numpad5), Binding::Key(QwertyScanCode::Period), Binding::Gamepad(GamepadButtonType::South)],
+            Action::Rest => vec![Binding::Key(QwertyScanCode::R), Binding::Gamepad(GamepadButtonType::East)],
+            Action::PickUp => vec![Binding::Key(QwertyScanCode::G), Binding::Key(QwertyScanCode::Comma), Binding::Gamepad(GamepadButtonType::West)],
+            Action::Descend => vec![Binding::Key(QwertyScanCode::E), Binding::Gamepad(GamepadButtonType::RightTrigger)],
+            Action::Ascend => vec![Binding::Key(QwertyScanCode::Q), Binding::Gamepad(GamepadButtonType::LeftTrigger)],
+            Action::Look => vec![Binding::Key(QwertyScanCode::X), Binding::Gamepad(GamepadButtonType::North)],
+            Action::Inventory => vec![Binding::Key(QwertyScanCode::I), Binding::Gamepad(GamepadButtonType::Select)],
+            Action::Pause => vec![Binding::Key(QwertyScanCode::Escape), Binding::Gamepad(GamepadButtonType::Start)]
+        };
+        Bindings {
+            actions: REBINDABLE.iter().map(|action| (*action, defaults(*action))).collect()
+        }
+    }
+}
+
+impl Bindings {
+    // Saved bindings, with any action they don't cover given its defaults. If they break the rules toggle keeps to,
+    // having been edited by hand, they're all thrown away for the defaults
+    pub fn load(pkv: &PkvStore) -> Bindings {
+        let Ok(saved) = pkv.get::<Bindings>(BINDINGS_KEY) else {
+            return Bindings::default();
+        };
+        Bindings::from_saved(saved).unwrap_or_else(|error| {
+            warn!("Ignoring the saved key bindings: {}", error);
+            Bindings::default()
+        })
+    }
+
+    fn from_saved(saved: Bindings) -> Result<Bindings, String> {
+        if let Some(action) = saved.actions.iter().find(|(_, bindings)| bindings.is_empty()).map(|(action, _)| *action) {
+            return Err(format!("{:?} needs at least one binding", action));
+        }
+        let mut bindings = Bindings::default();
+        // Defaults added since the bindings were saved, e.g. the vi-keys, give way to whatever the player put on those keys.
+        // An action that loses all of them is left unbound for the player to fill in from the controls menu
+        for defaults in bindings.actions.values_mut() {
+            defaults.retain(|binding| saved.bound_to(*binding).is_none());
+        }
+        bindings.actions.extend(saved.actions);
+        bindings.validate()?;
+        Ok(bindings)
+    }
+
+    // Nothing is bound to more than one action
+    fn validate(&self) -> Result<(), String> {
+        let mut bound_to: HashMap<Binding, Action> = HashMap::new();
+        for action in REBINDABLE {
+            for binding in self.actions.get(&action).map_or(&[][..], Vec::as_slice) {
+                match bound_to.insert(*binding, action) {
+                    Some(bound_action) if bound_action != action => {
+                        return Err(format!("{} is already used by {:?}", binding, bound_action));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, pkv: &mut PkvStore) {
+        if let Err(error) = pkv.set(BINDINGS_KEY, self) {
+            warn!("Couldn't save the key bindings: {:?}", error);
+        }
+    }
+
+    pub fn input_map(&self) -> InputMap<Action> {
+        let mut input_map = InputMap::default();
+        for (action, bindings) in &self.actions {
+            for binding in bindings {
+                match *binding {
+                    Binding::Key(key) => input_map.insert(key, *action),
+                    Binding::Gamepad(button) => input_map.insert(button, *action)
+                };
+            }
+        }
+        input_map
+    }
+
+    pub fn bound_to(&self, binding: Binding) -> Option<Action> {
+        self.actions.iter()
+            .find(|(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    // Adds the binding to the action, or takes it away if the action already has it
+    pub fn toggle(&mut self, action: Action, binding: Binding) -> Result<(), String> {
+        match self.bound_to(binding) {
+            Some(bound_action) if bound_action == action => {
+                let bindings = self.actions.entry(action).or_default();
+                if bindings.len() == 1 {
+                    return Err(format!("{:?} needs at least one binding", action));
+                }
+                bindings.retain(|bound| *bound != binding);
+            }
+            Some(bound_action) => return Err(format!("{} is already used by {:?}", binding, bound_action)),
+            None => self.actions.entry(action).or_default().push(binding)
+        }
+        Ok(())
+    }
+
+    fn describe(&self, action: Action) -> String {
+        self.actions.get(&action)
+            .map(|bindings| bindings.iter().map(Binding::to_string).collect::<Vec<_>>().join(", "))
+            .unwrap_or_default()
+    }
+}
+
+// Waiting for the key or button to add to, or take away from, an action
+#[derive(Resource)]
+pub struct Rebinding {
+    pub action: Action
+}
+
+pub fn is_controls_action(action: &str) -> bool {
+    action.starts_with(BIND_PREFIX) || action == RESET_BINDINGS
+}
+
+fn bind_action(action: Action) -> String {
+    format!("{}{:?}", BIND_PREFIX, action)
+}
+
+pub fn spawn_player(mut commands: Commands, pkv: Res<PkvStore>) {
+    let bindings = Bindings::load(&pkv);
+    commands.spawn(
+        InputManagerBundle::<Action> {
+            action_state: ActionState::default(),
+            input_map: bindings.input_map()
+        }
+    ).insert(Player);
+    commands.insert_resource(bindings);
+}
+
+fn apply_bindings(bindings: &Bindings, pkv: &mut PkvStore, input_maps: &mut Query<&mut InputMap<Action>, With<Player>>) {
+    bindings.save(pkv);
+    for mut input_map in input_maps {
+        *input_map = bindings.input_map();
+    }
+}
+
+pub fn act_on_controls_menu(
+    mut commands: Commands,
+    mut ev_selected: EventReader<MenuSelectedEvent>,
+    mut menu_state: ResMut<MenuState>,
+    mut bindings: ResMut<Bindings>,
+    mut pkv: ResMut<PkvStore>,
+    mut input_maps: Query<&mut InputMap<Action>, With<Player>>
+) {
+    for ev in ev_selected.read() {
+        if ev.action == RESET_BINDINGS {
+            *bindings = Bindings::default();
+            apply_bindings(&bindings, &mut pkv, &mut input_maps);
+            menu_state.note = None;
+        } else if let Some(action) = REBINDABLE.into_iter().find(|action| ev.action == bind_action(*action)) {
+            menu_state.prompt = Some(format!("Press a key or button to bind it to {:?}\nPressing one it already has unbinds it, Escape leaves it as it is", action));
+            commands.insert_resource(Rebinding { action });
+        }
+    }
+}
+
+// Has to run before the menu's own navigation, so the key being bound isn't also used to move around the menu
+pub fn capture_binding(
+    mut commands: Commands,
+    rebinding: Res<Rebinding>,
+    mut keys: ResMut<Input<KeyCode>>,
+    scan_codes: Res<Input<ScanCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    mut menu_state: ResMut<MenuState>,
+    mut bindings: ResMut<Bindings>,
+    mut pkv: ResMut<PkvStore>,
+    mut input_maps: Query<&mut InputMap<Action>, With<Player>>
+) {
+    let scan_code = scan_codes.get_just_pressed().next().copied();
+    let binding = match (scan_code, buttons.get_just_pressed().next()) {
+        (Some(scan_code), _) => BINDABLE_KEYS.into_iter().find(|key| ScanCode(*key as u32) == scan_code).map(Binding::Key),
+        (None, Some(button)) => Some(Binding::Gamepad(button.button_type)),
+        (None, None) => return
+    };
+    let pressed: Vec<KeyCode> = keys.get_just_pressed().copied().collect();
+    for key in pressed {
+        keys.clear_just_pressed(key);
+    }
+    menu_state.prompt = None;
+    commands.remove_resource::<Rebinding>();
+
+    // Escape backs out of rebinding, so the only way to get it back onto Pause is resetting the bindings
+    if binding == Some(Binding::Key(QwertyScanCode::Escape)) {
+        menu_state.note = None;
+        return;
+    }
+    menu_state.note = match binding.map(|binding| bindings.toggle(rebinding.action, binding)) {
+        Some(Ok(())) => {
+            apply_bindings(&bindings, &mut pkv, &mut input_maps);
+            None
+        }
+        Some(Err(error)) => Some(error),
+        None => Some("That key can't be bound".to_string())
+    };
+}
+
+// Keeps the controls menu showing what every action is bound to
+pub fn show_bindings(bindings: Res<Bindings>, mut menu_state: ResMut<MenuState>) {
+    for action in REBINDABLE {
+        menu_state.values.insert(bind_action(action), bindings.describe(action));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_rejects_conflicts_and_unbound_actions() {
+        let mut bindings = Bindings::default();
+        assert_eq!(bindings.toggle(Action::North, Binding::Key(QwertyScanCode::F)), Ok(()));
+        assert_eq!(bindings.toggle(Action::North, Binding::Gamepad(GamepadButtonType::C)), Ok(()));
+        assert_eq!(bindings.bound_to(Binding::Key(QwertyScanCode::F)), Some(Action::North));
+        assert_eq!(bindings.describe(Action::North), "W, Up, Numpad8, K, Pad DPadUp, F, Pad C");
+
+        assert!(bindings.toggle(Action::South, Binding::Key(QwertyScanCode::F)).is_err());
+        assert!(bindings.toggle(Action::Look, Binding::Key(QwertyScanCode::K)).is_err());
+        assert_eq!(bindings.toggle(Action::North, Binding::Key(QwertyScanCode::W)), Ok(()));
+        assert_eq!(bindings.bound_to(Binding::Key(QwertyScanCode::W)), None);
+
+        assert_eq!(bindings.toggle(Action::Pause, Binding::Gamepad(GamepadButtonType::Start)), Ok(()));
+        assert!(bindings.toggle(Action::Pause, Binding::Key(QwertyScanCode::Escape)).is_err());
+        assert_eq!(bindings.actions[&Action::Pause], vec![Binding::Key(QwertyScanCode::Escape)]);
+    }
+
+    #[test]
+    fn saved_bindings_are_checked_like_rebinding() {
+        let saved = |actions: Vec<(Action, Vec<Binding>)>| Bindings { actions: actions.into_iter().collect() };
+
+        let bindings = Bindings::from_saved(saved(vec![(Action::Look, vec![Binding::Key(QwertyScanCode::F)])])).unwrap();
+        assert_eq!(bindings.bound_to(Binding::Key(QwertyScanCode::F)), Some(Action::Look));
+        assert_eq!(bindings.bound_to(Binding::Key(QwertyScanCode::W)), Some(Action::North));
+
+        assert!(Bindings::from_saved(saved(vec![(Action::Look, vec![Binding::Key(QwertyScanCode::F)]), (Action::Descend, vec![Binding::Key(QwertyScanCode::F)])])).is_err());
+        assert!(Bindings::from_saved(saved(vec![(Action::Look, vec![])])).is_err());
+    }
+
+    #[test]
+    fn new_action_whose_defaults_are_taken_is_left_unbound() {
+        // Saved before Look existed, with both of its defaults put to use by the player
+        let saved = Bindings {
+            actions: [
+                (Action::Skip, vec![Binding::Key(QwertyScanCode::Space), Binding::Key(QwertyScanCode::X)]),
+                (Action::PickUp, vec![Binding::Key(QwertyScanCode::G), Binding::Gamepad(GamepadButtonType::North)])
+            ].into_iter().collect()
+        };
+        let mut bindings = Bindings::from_saved(saved).unwrap();
+        assert_eq!(bindings.actions[&Action::Look], vec![]);
+        assert_eq!(bindings.bound_to(Binding::Key(QwertyScanCode::X)), Some(Action::Skip));
+        assert_eq!(bindings.bound_to(Binding::Gamepad(GamepadButtonType::North)), Some(Action::PickUp));
+        assert_eq!(bindings.toggle(Action::Look, Binding::Key(QwertyScanCode::F)), Ok(()));
+        assert_eq!(bindings.actions[&Action::Look], vec![Binding::Key(QwertyScanCode::F)]);
+    }
+
+    #[test]
+    fn new_defaults_give_way_to_older_saved_bindings() {
+        // Saved before the vi-keys and the other actions had defaults, with K, E and X put to use by the player
+        let saved = Bindings {
+            actions: [
+                (Action::North, vec![Binding::Key(QwertyScanCode::W), Binding::Key(QwertyScanCode::Up), Binding::Key(QwertyScanCode::E)]),
+                (Action::South, vec![Binding::Key(QwertyScanCode::S), Binding::Key(QwertyScanCode::Down), Binding::Key(QwertyScanCode::K)]),
+                (Action::East, vec![Binding::Key(QwertyScanCode::D), Binding::Key(QwertyScanCode::Right)]),
+                (Action::West, vec![Binding::Key(QwertyScanCode::A), Binding::Key(QwertyScanCode::Left)]),
+                (Action::Skip, vec![Binding::Key(QwertyScanCode::Space), Binding::Key(QwertyScanCode::X)]),
+                (Action::Pause, vec![Binding::Key(QwertyScanCode::Escape)])
+            ].into_iter().collect()
+        };
+        let bindings = Bindings::from_saved(saved).unwrap();
+        assert_eq!(bindings.bound_to(Binding::Key(QwertyScanCode::K)), Some(Action::South));
+        assert_eq!(bindings.bound_to(Binding::Key(QwertyScanCode::E)), Some(Action::North));
+        assert_eq!(bindings.bound_to(Binding::Key(QwertyScanCode::X)), Some(Action::Skip));
+        assert_eq!(bindings.actions[&Action::Descend], vec![Binding::Gamepad(GamepadButtonType::RightTrigger)]);
+        assert_eq!(bindings.actions[&Action::Look], vec![Binding::Gamepad(GamepadButtonType::North)]);
+        assert_eq!(bindings.bound_to(Binding::Key(QwertyScanCode::U)), Some(Action::NorthEast));
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::prelude::{Commands, Entity, Event, EventReader, EventWriter, Query, ResMut, With, Without};
+use bevy_ecs_tilemap::tiles::{TilePos, TileStorage};
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::GlobalEntropy;
+use rand_core::RngCore;
+use worldgen::rand_range;
+use crate::lifeform::Lifeform;
+use crate::world::{LifeformLayer, TerrainLayer};
+
+#[derive(Event)]
+pub struct AttackEvent {
+    pub attacker: Entity,
+    pub defender: Entity
+}
+
+#[derive(Event)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    pub killer: Entity
+}
+
+pub fn compute_damage<R: RngCore>(strength: u32, defense: u32, rng: &mut R) -> u32 {
+    // Roll somewhere between half and full strength, then let defense soak it up
+    // A hit always does at least 1 damage so fights can't stall forever
+    let min_attack = strength / 2;
+    let attack = rand_range(min_attack, strength - min_attack + 1, rng);
+    std::cmp::max(attack.saturating_sub(defense), 1)
+}
+
+pub fn resolve_attacks(
+    mut ev_attack: EventReader<AttackEvent>,
+    mut ev_death: EventWriter<DeathEvent>,
+    mut lifeforms: Query<&mut Lifeform>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>
+) {
+    for ev in ev_attack.read() {
+        let Ok([attacker, mut defender]) = lifeforms.get_many_mut([ev.attacker, ev.defender]) else {
+            continue;
+        };
+        // Something that died earlier this turn can't attack or be attacked again
+        if attacker.health == 0 || defender.health == 0 {
+            continue;
+        }
+
+        let damage = compute_damage(attacker.strength, defender.defense, &mut *rng);
+        defender.health = defender.health.saturating_sub(damage);
+        if defender.health == 0 {
+            ev_death.send(DeathEvent { entity: ev.defender, killer: ev.attacker });
+        }
+    }
+}
+
+pub fn remove_dead(
+    mut commands: Commands,
+    mut ev_death: EventReader<DeathEvent>,
+    mut lifeform_layer: Query<&mut TileStorage, (With<LifeformLayer>, Without<TerrainLayer>)>,
+    positions: Query<&TilePos>
+) {
+    let Ok(mut lifeform_storage) = lifeform_layer.get_single_mut() else {
+        return;
+    };
+
+    for ev in ev_death.read() {
+        if let Ok(tile_pos) = positions.get(ev.entity) {
+            if lifeform_storage.checked_get(tile_pos) == Some(ev.entity) {
+                lifeform_storage.remove(tile_pos);
+            }
+        }
+        commands.entity(ev.entity).despawn();
+    }
+}
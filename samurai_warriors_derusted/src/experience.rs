@@ -0,0 +1,97 @@
+use bevy::prelude::{Entity, Event, EventReader, EventWriter, Query, Res, Resource, With};
+use crate::combat::DeathEvent;
+use crate::lifeform::{Lifeform, PlayerCharacter};
+
+#[derive(Event)]
+pub struct ExperienceEvent {
+    pub entity: Entity,
+    pub amount: u32
+}
+
+#[derive(Event)]
+pub struct LevelUpEvent {
+    pub entity: Entity,
+    pub level: u32
+}
+
+#[derive(Resource)]
+pub struct LevelingConfig {
+    // Experience needed to go from level 0 to level 1
+    pub base_experience: u32,
+    // Each level needs this many times the experience of the one before
+    pub experience_growth: f32,
+    pub health_per_level: u32,
+    pub strength_per_level: u32,
+    pub defense_per_level: u32
+}
+
+impl Default for LevelingConfig {
+    fn default() -> Self {
+        LevelingConfig {
+            base_experience: 3,
+            experience_growth: 1.5,
+            health_per_level: 8,
+            strength_per_level: 2,
+            defense_per_level: 1
+        }
+    }
+}
+
+impl LevelingConfig {
+    pub fn experience_to_next_level(&self, level: u32) -> u32 {
+        let needed = self.base_experience as f32 * self.experience_growth.powi(level as i32);
+        std::cmp::max(needed.round() as u32, 1)
+    }
+
+    pub fn level_up(&self, lifeform: &mut Lifeform) {
+        lifeform.level += 1;
+        lifeform.health += self.health_per_level;
+        lifeform.max_health += self.health_per_level;
+        lifeform.strength += self.strength_per_level;
+        lifeform.defense += self.defense_per_level;
+    }
+}
+
+pub fn reward_experience(
+    mut ev_death: EventReader<DeathEvent>,
+    mut ev_experience: EventWriter<ExperienceEvent>,
+    lifeforms: Query<&Lifeform>,
+    player: Query<(), With<PlayerCharacter>>
+) {
+    for ev in ev_death.read() {
+        if !player.contains(ev.killer) {
+            continue;
+        }
+        // An enemy's experience is what it's worth when killed, stronger enemies are worth more
+        if let Ok(victim) = lifeforms.get(ev.entity) {
+            ev_experience.send(ExperienceEvent {
+                entity: ev.killer,
+                amount: victim.experience * (victim.level + 1)
+            });
+        }
+    }
+}
+
+pub fn gain_experience(
+    mut ev_experience: EventReader<ExperienceEvent>,
+    mut ev_level_up: EventWriter<LevelUpEvent>,
+    mut lifeforms: Query<&mut Lifeform>,
+    config: Res<LevelingConfig>
+) {
+    for ev in ev_experience.read() {
+        let Ok(mut lifeform) = lifeforms.get_mut(ev.entity) else {
+            continue;
+        };
+
+        lifeform.experience += ev.amount;
+        loop {
+            let needed = config.experience_to_next_level(lifeform.level);
+            if lifeform.experience < needed {
+                break;
+            }
+            lifeform.experience -= needed;
+            config.level_up(&mut lifeform);
+            ev_level_up.send(LevelUpEvent { entity: ev.entity, level: lifeform.level });
+        }
+    }
+}
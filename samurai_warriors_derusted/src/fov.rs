@@ -0,0 +1,301 @@
+use bevy::prelude::{Changed, Color, Event, EventReader, Query, Ref, Res, ResMut, Resource, With, Without, info};
+use bevy_ecs_tilemap::prelude::{TileColor, TilePos, TileStorage, TileVisible};
+use crate::lifeform::{Lifeform, PlayerCharacter};
+use crate::pathfinding::Point;
+use crate::tile_data::TileData;
+use crate::world::{LifeformLayer, TerrainLayer};
+
+pub const FOV_RADIUS: u32 = 10;
+
+const EXPLORED_TINT: Color = Color::rgb(0.35, 0.35, 0.45);
+
+// Multipliers that map the first octant onto each of the 8 octants around the origin
+const OCTANTS: [(i64, i64, i64, i64); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1)
+];
+
+#[derive(Default, Eq, PartialEq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub enum TileVisibility {
+    #[default]
+    Unseen,
+    Explored,
+    Visible
+}
+
+#[derive(Resource, Clone)]
+pub struct FogOfWar {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<TileVisibility>
+}
+
+impl FogOfWar {
+    pub fn new(width: usize, height: usize) -> FogOfWar {
+        FogOfWar {
+            width,
+            height,
+            tiles: vec![TileVisibility::Unseen; width * height]
+        }
+    }
+
+    pub fn get(&self, point: Point) -> TileVisibility {
+        if point.0 < self.width && point.1 < self.height {
+            self.tiles[point.0 * self.height + point.1]
+        } else {
+            TileVisibility::Unseen
+        }
+    }
+
+    // Everything visible last time becomes explored, then the new field of view is marked visible
+    pub fn update(&mut self, visible: &[Point]) {
+        for tile in &mut self.tiles {
+            if *tile == TileVisibility::Visible {
+                *tile = TileVisibility::Explored;
+            }
+        }
+        for point in visible {
+            if point.0 < self.width && point.1 < self.height {
+                self.tiles[point.0 * self.height + point.1] = TileVisibility::Visible;
+            }
+        }
+    }
+}
+
+struct FovArea<'a> {
+    origin: (i64, i64),
+    radius: i64,
+    width: i64,
+    height: i64,
+    is_opaque: &'a dyn Fn(Point) -> bool,
+    visible: Vec<bool>
+}
+
+impl<'a> FovArea<'a> {
+    fn in_bounds(&self, x: i64, y: i64) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    // Recursive shadowcasting over one octant, scanning rows outwards between the start and end slopes
+    fn cast_light(&mut self, row: i64, mut start: f64, end: f64, multipliers: (i64, i64, i64, i64)) {
+        if start < end {
+            return;
+        }
+        let (xx, xy, yx, yy) = multipliers;
+        let mut new_start = 0.0;
+
+        for distance in row..=self.radius {
+            let delta_y = -distance;
+            let mut blocked = false;
+
+            for delta_x in -distance..=0 {
+                let x = self.origin.0 + delta_x * xx + delta_y * xy;
+                let y = self.origin.1 + delta_x * yx + delta_y * yy;
+                let left_slope = (delta_x as f64 - 0.5) / (delta_y as f64 + 0.5);
+                let right_slope = (delta_x as f64 + 0.5) / (delta_y as f64 - 0.5);
+
+                if start < right_slope {
+                    continue;
+                } else if end > left_slope {
+                    break;
+                }
+
+                let in_bounds = self.in_bounds(x, y);
+                if in_bounds && delta_x * delta_x + delta_y * delta_y <= self.radius * self.radius {
+                    self.visible[(x * self.height + y) as usize] = true;
+                }
+
+                let opaque = !in_bounds || (self.is_opaque)((x as usize, y as usize));
+                if blocked {
+                    if opaque {
+                        new_start = right_slope;
+                    } else {
+                        blocked = false;
+                        start = new_start;
+                    }
+                } else if opaque && distance < self.radius {
+                    blocked = true;
+                    self.cast_light(distance + 1, start, left_slope, multipliers);
+                    new_start = right_slope;
+                }
+            }
+
+            if blocked {
+                break;
+            }
+        }
+    }
+}
+
+// Every tile visible from the origin within the radius, opaque tiles are visible but hide what's behind them
+pub fn compute_fov(origin: Point, radius: u32, width: usize, height: usize, is_opaque: &dyn Fn(Point) -> bool) -> Vec<Point> {
+    if origin.0 >= width || origin.1 >= height {
+        return vec![];
+    }
+
+    let mut area = FovArea {
+        origin: (origin.0 as i64, origin.1 as i64),
+        radius: radius as i64,
+        width: width as i64,
+        height: height as i64,
+        is_opaque,
+        visible: vec![false; width * height]
+    };
+    area.visible[origin.0 * height + origin.1] = true;
+    for multipliers in OCTANTS {
+        area.cast_light(1, 1.0, 0.0, multipliers);
+    }
+
+    let mut output = vec![];
+    for x in 0..width {
+        for y in 0..height {
+            if area.visible[x * height + y] {
+                output.push((x, y));
+            }
+        }
+    }
+    output
+}
+
+// Look was pressed, everything in sight gets described
+#[derive(Event)]
+pub struct LookEvent;
+
+pub fn look_around(
+    mut ev_look: EventReader<LookEvent>,
+    fog_of_war: Res<FogOfWar>,
+    player: Query<&TilePos, With<PlayerCharacter>>,
+    lifeforms: Query<(&TilePos, &Lifeform), Without<PlayerCharacter>>
+) {
+    ev_look.clear();
+    let Ok(player_pos) = player.get_single() else {
+        return;
+    };
+    let in_sight: Vec<String> = lifeforms.iter()
+        .filter(|(tile_pos, _)| fog_of_war.get((tile_pos.x as usize, tile_pos.y as usize)) == TileVisibility::Visible)
+        .map(|(tile_pos, lifeform)| {
+            let distance = std::cmp::max(tile_pos.x.abs_diff(player_pos.x), tile_pos.y.abs_diff(player_pos.y));
+            format!("a level {} enemy with {} health {} tiles away", lifeform.level, lifeform.health, distance)
+        })
+        .collect();
+    if in_sight.is_empty() {
+        info!("Nothing else is in sight");
+    } else {
+        info!("You see {}", in_sight.join(", "));
+    }
+}
+
+// Sight only changes when the player moves (or arrives on a floor), and enemies only need showing or hiding when someone moves
+pub fn update_field_of_view(
+    mut fog_of_war: ResMut<FogOfWar>,
+    player: Query<Ref<TilePos>, With<PlayerCharacter>>,
+    moved_lifeforms: Query<(), (With<Lifeform>, Changed<TilePos>)>,
+    terrain_layer: Query<&TileStorage, (With<TerrainLayer>, Without<LifeformLayer>)>,
+    tile_data: Query<&TileData>,
+    mut terrain_tiles: Query<(&TilePos, &mut TileVisible, &mut TileColor), Without<Lifeform>>,
+    mut lifeform_tiles: Query<(&TilePos, &mut TileVisible), With<Lifeform>>
+) {
+    let (Ok(player_pos), Ok(terrain_storage)) = (player.get_single(), terrain_layer.get_single()) else {
+        return;
+    };
+    if player_pos.is_changed() {
+        see_from(&player_pos, &mut fog_of_war, terrain_storage, &tile_data, &mut terrain_tiles);
+    } else if moved_lifeforms.is_empty() {
+        return;
+    }
+
+    for (tile_pos, mut tile_visible) in &mut lifeform_tiles {
+        let show = fog_of_war.get((tile_pos.x as usize, tile_pos.y as usize)) == TileVisibility::Visible;
+        if tile_visible.0 != show {
+            tile_visible.0 = show;
+        }
+    }
+}
+
+fn see_from(
+    player_pos: &TilePos,
+    fog_of_war: &mut FogOfWar,
+    terrain_storage: &TileStorage,
+    tile_data: &Query<&TileData>,
+    terrain_tiles: &mut Query<(&TilePos, &mut TileVisible, &mut TileColor), Without<Lifeform>>
+) {
+    let is_opaque = |point: Point| {
+        match terrain_storage.checked_get(&TilePos { x: point.0 as u32, y: point.1 as u32 }) {
+            Some(entity) => match tile_data.get(entity) {
+                Ok(tile_data) => tile_data.opaque,
+                Err(_) => true
+            },
+            None => true
+        }
+    };
+    let visible = compute_fov((player_pos.x as usize, player_pos.y as usize), FOV_RADIUS, fog_of_war.width, fog_of_war.height, &is_opaque);
+    fog_of_war.update(&visible);
+
+    // Only touch tiles that actually change, every change gets sent to the renderer
+    for (tile_pos, mut tile_visible, mut tile_color) in terrain_tiles {
+        let (show, color) = match fog_of_war.get((tile_pos.x as usize, tile_pos.y as usize)) {
+            TileVisibility::Unseen => (false, Color::WHITE),
+            TileVisibility::Explored => (true, EXPLORED_TINT),
+            TileVisibility::Visible => (true, Color::WHITE)
+        };
+        if tile_visible.0 != show {
+            tile_visible.0 = show;
+        }
+        if tile_color.0 != color {
+            tile_color.0 = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // '#' is a wall, anything else is floor
+    // Rows are listed top to bottom, so the last row is y = 0
+    fn fov_from(rows: &[&str], origin: Point, radius: u32) -> Vec<Point> {
+        let height = rows.len();
+        let width = rows[0].len();
+        let is_opaque = |point: Point| rows[height - 1 - point.1].as_bytes()[point.0] == b'#';
+        compute_fov(origin, radius, width, height, &is_opaque)
+    }
+
+    #[test]
+    fn origin_is_always_visible() {
+        assert_eq!(fov_from(&["..."], (1, 0), 0), vec![(1, 0)]);
+        let walled_in = fov_from(&[
+            "###",
+            "#.#",
+            "###"
+        ], (1, 1), FOV_RADIUS);
+        assert_eq!(walled_in.len(), 9);
+        assert!(walled_in.contains(&(1, 1)));
+    }
+
+    #[test]
+    fn walls_are_seen_but_hide_what_is_behind_them() {
+        let visible = fov_from(&[
+            ".........",
+            "....#....",
+            "........."
+        ], (0, 1), FOV_RADIUS);
+        assert!(visible.contains(&(3, 1)));
+        assert!(visible.contains(&(4, 1)));
+        assert!(!visible.contains(&(5, 1)));
+        assert!(!visible.contains(&(8, 1)));
+    }
+
+    #[test]
+    fn nothing_is_seen_past_the_radius() {
+        let visible = fov_from(&["..............."], (0, 0), 5);
+        assert!(visible.contains(&(5, 0)));
+        assert!(!visible.contains(&(6, 0)));
+        assert_eq!(visible.len(), 6);
+    }
+}
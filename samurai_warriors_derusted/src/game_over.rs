@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use bevy_pkv::PkvStore;
+use leafwing_input_manager::prelude::ActionState;
+use crate::{Action, AppState};
+use crate::combat::DeathEvent;
+use crate::lifeform::{Lifeform, Player, PlayerCharacter};
+use crate::save::delete_saved_run;
+use crate::seed::RunSeed;
+use crate::turn::{Resting, TurnScheduler};
+use crate::world::{Dungeon, FloorEntities, despawn_floor};
+
+// How a run came to an end
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum RunOutcome {
+    Lost(String),
+    // Took the Exit of the final floor
+    Won
+}
+
+#[derive(Resource)]
+pub struct RunStats {
+    pub deepest_floor: u32,
+    pub kills: u32,
+    pub outcome: Option<RunOutcome>
+}
+
+impl Default for RunStats {
+    fn default() -> Self {
+        RunStats {
+            deepest_floor: 1,
+            kills: 0,
+            outcome: None
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct GameOverEvent {
+    pub outcome: RunOutcome
+}
+
+#[derive(Component)]
+pub struct RunSummaryUi;
+
+pub fn track_deaths(
+    mut ev_death: EventReader<DeathEvent>,
+    mut ev_game_over: EventWriter<GameOverEvent>,
+    mut run_stats: ResMut<RunStats>,
+    player: Query<(), With<PlayerCharacter>>,
+    lifeforms: Query<&Lifeform>
+) {
+    for ev in ev_death.read() {
+        if player.contains(ev.entity) {
+            let cause = match lifeforms.get(ev.killer) {
+                Ok(killer) => format!("Slain by a level {} enemy", killer.level),
+                Err(_) => "Slain by an unknown foe".to_string()
+            };
+            ev_game_over.send(GameOverEvent { outcome: RunOutcome::Lost(cause) });
+        } else if player.contains(ev.killer) {
+            run_stats.kills += 1;
+        }
+    }
+}
+
+pub fn game_over(
+    mut ev_game_over: EventReader<GameOverEvent>,
+    mut run_stats: ResMut<RunStats>,
+    mut next_state: ResMut<NextState<AppState>>
+) {
+    for ev in ev_game_over.read() {
+        run_stats.outcome = Some(ev.outcome.clone());
+        next_state.set(AppState::GameOver);
+    }
+}
+
+pub fn end_run(
+    mut commands: Commands,
+    floor: FloorEntities,
+    mut pkv: ResMut<PkvStore>,
+    mut next_state: ResMut<NextState<AppState>>
+) {
+    // Won or lost, the run is over and nothing is left behind to continue from
+    delete_saved_run(&mut pkv);
+    despawn_floor(&mut commands, &floor);
+    next_state.set(AppState::RunSummary);
+}
+
+pub fn show_run_summary(
+    mut commands: Commands,
+    run_stats: Res<RunStats>,
+    run_seed: Res<RunSeed>,
+    scheduler: Res<TurnScheduler>
+) {
+    let (title, cause) = match &run_stats.outcome {
+        Some(RunOutcome::Won) => ("VICTORY", "Escaped the dungeon".to_string()),
+        Some(RunOutcome::Lost(cause)) => ("GAME OVER", cause.clone()),
+        None => ("GAME OVER", "Still alive".to_string())
+    };
+    let summary = format!(
+        "{}\n\n{}\nDepth reached: {}\nKills: {}\nTurns taken: {}\nSeed: {}\n\nPress Space to start a new run",
+        title, cause, run_stats.deepest_floor, run_stats.kills, scheduler.turns, run_seed.current.unwrap_or_default()
+    );
+
+    commands.spawn((
+        TextBundle::from_section(summary, TextStyle {
+            font_size: 32.0,
+            color: Color::WHITE,
+            ..default()
+        }).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            left: Val::Px(40.0),
+            ..default()
+        }),
+        RunSummaryUi
+    ));
+}
+
+pub fn leave_run_summary(
+    query: Query<&ActionState<Action>, With<Player>>,
+    mut next_state: ResMut<NextState<AppState>>
+) {
+    let action_state = query.single();
+    if action_state.just_pressed(Action::Skip) {
+        next_state.set(AppState::Generate);
+    }
+}
+
+pub fn reset_run(
+    mut commands: Commands,
+    summary_ui: Query<Entity, With<RunSummaryUi>>,
+    mut run_seed: ResMut<RunSeed>
+) {
+    for entity in &summary_ui {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.insert_resource(RunStats::default());
+    commands.insert_resource(Dungeon::default());
+    commands.insert_resource(TurnScheduler::default());
+    commands.remove_resource::<Resting>();
+    run_seed.current = None;
+}
@@ -1,54 +1,241 @@
-use bevy::prelude::Component;
+use bevy::prelude::{Component, Entity, Event, EventReader, EventWriter, Query, With, Without};
+use bevy_ecs_tilemap::prelude::{TileTextureIndex, TilemapSize};
 use bevy_ecs_tilemap::tiles::{TilePos, TileStorage};
-use crate::worldgen::TerrainData;
+use crate::combat::AttackEvent;
+use crate::tile_data::{TerrainData, TileData};
+use crate::turn::TurnTakenEvent;
+use crate::world::{LifeformLayer, TerrainLayer};
+use worldgen::LifeformSpawn;
 
+// Marks the entity holding the player's input state
 #[derive(Component)]
 pub struct Player;
 
-struct Enemy;
+// Marks the tile entity the player controls on the lifeform layer
+#[derive(Component)]
+pub struct PlayerCharacter;
+
+#[derive(Component)]
+pub struct Enemy;
 
+#[derive(Component, Clone)]
 pub struct Lifeform {
     pub texture: TerrainData,
     pub position: TilePos,
     pub health: u32,
+    pub max_health: u32,
     pub strength: u32,
     pub defense: u32,
     pub level: u32,
     pub experience: u32
 }
 
-enum Direction {
+impl From<LifeformSpawn> for Lifeform {
+    // Textures are picked afterwards, once the asset data is at hand
+    fn from(spawn: LifeformSpawn) -> Lifeform {
+        Lifeform {
+            texture: TerrainData {
+                texture: TileTextureIndex(0),
+                tile_data: spawn.tile_data
+            },
+            position: TilePos { x: spawn.x as u32, y: spawn.y as u32 },
+            health: spawn.health,
+            max_health: spawn.health,
+            strength: spawn.strength,
+            defense: spawn.defense,
+            level: spawn.level,
+            experience: spawn.experience
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Direction {
     North,
     South,
     East,
-    West
+    West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest
 }
 
-fn move_lifeform (direction: Direction, tile_pos: &mut TilePos, map_tile_storage: TileStorage, lifeform_tile_storage: TileStorage) -> bool {
-    let mut next_pos = tile_pos.clone();
-    match direction {
-        Direction::North => {
-            next_pos.y -= 1;
+impl Direction {
+    // Tilemaps grow upwards, so North is +y
+    pub fn offset(&self) -> (i64, i64) {
+        match self {
+            Direction::North => (0, 1),
+            Direction::South => (0, -1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+            Direction::NorthEast => (1, 1),
+            Direction::NorthWest => (-1, 1),
+            Direction::SouthEast => (1, -1),
+            Direction::SouthWest => (-1, -1)
         }
-        Direction::South => {
-            next_pos.y += 1;
+    }
+
+    pub fn step(&self, tile_pos: &TilePos, map_size: &TilemapSize) -> Option<TilePos> {
+        let (offset_x, offset_y) = self.offset();
+        let next_pos = TilePos {
+            x: tile_pos.x.checked_add_signed(offset_x as i32)?,
+            y: tile_pos.y.checked_add_signed(offset_y as i32)?
+        };
+
+        if next_pos.within_map_bounds(map_size) {
+            Some(next_pos)
+        } else {
+            None
         }
-        Direction::East => {
-            next_pos.x += 1;
+    }
+
+    // The direction of a neighboring tile, if it is a neighbor at all
+    pub fn towards(from: &TilePos, to: &TilePos) -> Option<Direction> {
+        match (to.x as i64 - from.x as i64, to.y as i64 - from.y as i64) {
+            (0, 1) => Some(Direction::North),
+            (0, -1) => Some(Direction::South),
+            (1, 0) => Some(Direction::East),
+            (-1, 0) => Some(Direction::West),
+            (1, 1) => Some(Direction::NorthEast),
+            (-1, 1) => Some(Direction::NorthWest),
+            (1, -1) => Some(Direction::SouthEast),
+            (-1, -1) => Some(Direction::SouthWest),
+            _ => None
         }
-        Direction::West => {
-            next_pos.x -= 1;
+    }
+
+    // The two straight moves a diagonal is made of, straight moves have none
+    fn sides(&self) -> Option<(Direction, Direction)> {
+        match self {
+            Direction::NorthEast => Some((Direction::North, Direction::East)),
+            Direction::NorthWest => Some((Direction::North, Direction::West)),
+            Direction::SouthEast => Some((Direction::South, Direction::East)),
+            Direction::SouthWest => Some((Direction::South, Direction::West)),
+            _ => None
         }
     }
+}
+
+// Diagonal moves can't squeeze past the corner of a wall, both tiles either side of the move have to be open
+fn cuts_corner(direction: Direction, tile_pos: &TilePos, map_size: &TilemapSize, passable: impl Fn(&TilePos) -> bool) -> bool {
+    let Some((vertical, horizontal)) = direction.sides() else {
+        return false;
+    };
+    [vertical, horizontal].iter().any(|side| {
+        !side.step(tile_pos, map_size).is_some_and(|side_pos| passable(&side_pos))
+    })
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum MoveResult {
+    Moved(TilePos),
+    Blocked,
+    Occupied(Entity)
+}
+
+#[derive(Event)]
+pub struct MoveEvent {
+    pub entity: Entity,
+    pub direction: Direction
+}
+
+fn move_lifeform(direction: Direction, tile_pos: &TilePos, map_tile_storage: &TileStorage, lifeform_tile_storage: &TileStorage, tiles: &Query<&TileData>) -> MoveResult {
+    let Some(next_pos) = direction.step(tile_pos, &map_tile_storage.size) else {
+        return MoveResult::Blocked;
+    };
+    let terrain_passable = |side_pos: &TilePos| map_tile_storage.checked_get(side_pos)
+        .is_some_and(|map_entity| tiles.get(map_entity).is_ok_and(|tile_data| tile_data.passable));
+    if cuts_corner(direction, tile_pos, &map_tile_storage.size, terrain_passable) {
+        return MoveResult::Blocked;
+    }
+
     if let Some(map_entity) = map_tile_storage.checked_get(&next_pos) {
-        // Check if entity is passable
-        return if lifeform_tile_storage.checked_get(&next_pos).is_none() {
-            true
+        let passable = match tiles.get(map_entity) {
+            Ok(tile_data) => tile_data.passable,
+            Err(_) => false
+        };
+        if !passable {
+            return MoveResult::Blocked;
+        }
+
+        return if let Some(lifeform_entity) = lifeform_tile_storage.checked_get(&next_pos) {
+            MoveResult::Occupied(lifeform_entity)
         } else {
-            // TODO: Send an event to trigger an attack
-            false
+            MoveResult::Moved(next_pos)
         }
     }
 
-    return false;
-}
\ No newline at end of file
+    MoveResult::Blocked
+}
+
+pub fn move_lifeforms(
+    mut ev_move: EventReader<MoveEvent>,
+    mut ev_turn_taken: EventWriter<TurnTakenEvent>,
+    mut ev_attack: EventWriter<AttackEvent>,
+    mut lifeforms: Query<(&mut TilePos, &mut Lifeform)>,
+    enemies: Query<(), With<Enemy>>,
+    mut lifeform_layer: Query<&mut TileStorage, (With<LifeformLayer>, Without<TerrainLayer>)>,
+    terrain_layer: Query<&TileStorage, (With<TerrainLayer>, Without<LifeformLayer>)>,
+    tiles: Query<&TileData>
+) {
+    let (Ok(mut lifeform_storage), Ok(terrain_storage)) = (lifeform_layer.get_single_mut(), terrain_layer.get_single()) else {
+        return;
+    };
+
+    for ev in ev_move.read() {
+        let Ok((mut tile_pos, mut lifeform)) = lifeforms.get_mut(ev.entity) else {
+            continue;
+        };
+
+        match move_lifeform(ev.direction, &tile_pos, terrain_storage, &lifeform_storage, &tiles) {
+            MoveResult::Moved(next_pos) => {
+                lifeform_storage.remove(&tile_pos);
+                lifeform_storage.set(&next_pos, ev.entity);
+                *tile_pos = next_pos;
+                lifeform.position = next_pos;
+                ev_turn_taken.send(TurnTakenEvent { entity: ev.entity });
+            }
+            MoveResult::Occupied(other) => {
+                // Bumping into something hostile is an attack, enemies don't fight each other
+                if enemies.contains(ev.entity) != enemies.contains(other) {
+                    ev_attack.send(AttackEvent { attacker: ev.entity, defender: other });
+                    ev_turn_taken.send(TurnTakenEvent { entity: ev.entity });
+                }
+            }
+            MoveResult::Blocked => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP_SIZE: TilemapSize = TilemapSize { x: 3, y: 3 };
+
+    #[test]
+    fn diagonals_step_and_reverse() {
+        let center = TilePos { x: 1, y: 1 };
+        for direction in [Direction::NorthEast, Direction::NorthWest, Direction::SouthEast, Direction::SouthWest] {
+            let next_pos = direction.step(&center, &MAP_SIZE).unwrap();
+            assert_eq!(Direction::towards(&center, &next_pos), Some(direction));
+        }
+        assert_eq!(Direction::NorthEast.step(&center, &MAP_SIZE), Some(TilePos { x: 2, y: 2 }));
+        assert_eq!(Direction::SouthWest.step(&TilePos { x: 0, y: 1 }, &MAP_SIZE), None);
+        assert_eq!(Direction::NorthEast.step(&TilePos { x: 2, y: 1 }, &MAP_SIZE), None);
+    }
+
+    #[test]
+    fn diagonals_cannot_cut_wall_corners() {
+        let center = TilePos { x: 1, y: 1 };
+        let wall = TilePos { x: 1, y: 2 };
+        let open = |tile_pos: &TilePos| *tile_pos != wall;
+        assert!(cuts_corner(Direction::NorthEast, &center, &MAP_SIZE, open));
+        assert!(cuts_corner(Direction::NorthWest, &center, &MAP_SIZE, open));
+        assert!(!cuts_corner(Direction::SouthEast, &center, &MAP_SIZE, open));
+        assert!(!cuts_corner(Direction::North, &center, &MAP_SIZE, open));
+        // Off the edge of the map counts as a wall
+        assert!(cuts_corner(Direction::SouthWest, &TilePos { x: 0, y: 1 }, &MAP_SIZE, |_: &TilePos| true));
+    }
+}
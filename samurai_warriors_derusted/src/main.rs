@@ -1,13 +1,31 @@
 mod tile_data;
-mod worldgen;
 mod lifeform;
 mod utils;
 mod world;
-
-use crate::lifeform::Player;
-use crate::tile_data::{RelatedTextureData, TileTextureData, TextureArray, WorldState};
+mod turn;
+mod combat;
+mod experience;
+mod game_over;
+mod ai;
+mod pathfinding;
+mod fov;
+mod save;
+mod seed;
+mod replay;
+mod worldgen_cli;
+mod profiles;
+mod menu;
+mod bindings;
+
+use crate::lifeform::{Direction, Enemy, MoveEvent, Player, PlayerCharacter, move_lifeforms};
+use crate::tile_data::{FloorLifeform, RelatedTextureData, TerrainData, TileTextureData, TileTextureDataExt, TextureArray, WorldState};
 use std::any::TypeId;
 use bevy::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::render::RenderPlugin;
+use bevy::render::settings::WgpuSettings;
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
 use asset_loading_plugin::*;
 use asset_loading_plugin::index::*;
 use asset_loading_plugin::loader::*;
@@ -16,11 +34,26 @@ use bevy_common_assets::json::JsonAssetPlugin;
 use bevy_rand::prelude::*;
 use bevy_prng::ChaCha8Rng;
 use leafwing_input_manager::prelude::*;
-use crate::worldgen::{RoomGenerator};
+use bevy_pkv::PkvStore;
+use menu_system_plugin::MenuSystemPlugin;
+use menu_system_plugin::navigation::{MenuSet, menu_open};
+use menu_system_plugin::sprite::SpriteMenuRenderer;
+use worldgen::{DungeonProfiles, GeneratorConfig, floor_seed};
 use crate::lifeform::Lifeform;
-
-const MAP_X: u32 = 100;
-const MAP_Y: u32 = 40;
+use crate::turn::{ActorReadyEvent, DEFAULT_SPEED, Energy, Resting, TurnScheduler, TurnSet, TurnTakenEvent, advance_turns, keep_resting, player_ready, regenerate, spend_energy};
+use crate::world::{DownStairs, Dungeon, LifeformLayer, MapConfig, TerrainLayer, UpStairs, UseStairsEvent, map_config, take_stairs};
+use crate::combat::{AttackEvent, DeathEvent, remove_dead, resolve_attacks};
+use crate::experience::{ExperienceEvent, LevelUpEvent, LevelingConfig, gain_experience, reward_experience};
+use crate::ai::{Ai, enemy_turns};
+use crate::fov::{FogOfWar, LookEvent, look_around, update_field_of_view};
+use crate::save::{SaveAndQuitEvent, load_run, save_on_quit};
+use crate::replay::{ReplayPlayback, ReplayRecorder, finish_playback, finish_recording, finish_recording_on_quit, load_replay, playback_exhausted, replay_options, start_recording};
+use crate::worldgen_cli::WORLDGEN_COMMAND;
+use crate::seed::{RunSeed, requested_seed, spawn_seed_text, start_run, update_seed_text};
+use crate::bindings::{Bindings, Rebinding, act_on_controls_menu, capture_binding, show_bindings, spawn_player};
+use crate::menu::{MENU_PATH, SeedEntry, act_on_menu, close_menu, enter_seed, leave_run, open_main_menu, open_pause_menu};
+use crate::profiles::{DungeonProfilesHandle, load_dungeon_profiles, report_profile_changes, wait_for_dungeon_profiles};
+use crate::game_over::{GameOverEvent, RunStats, end_run, game_over, leave_run_summary, reset_run, show_run_summary, track_deaths};
 
 type LoadedAssetData = LoadedData<TileTextureData>;
 type TileAssetLoadedEvent = AssetLoadedEvent<TileTextureData>;
@@ -31,62 +64,185 @@ enum AppState {
     AssetLoading,
     AssetLoaded,
     AssetPrepped,
-    //Menu,
+    Menu,
+    Continue,
     Generate,
-    Play
+    Play,
+    GameOver,
+    RunSummary
 }
 
-#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect, serde::Serialize, serde::Deserialize)]
 enum Action {
     North,
     South,
     East,
     West,
     Skip,
-    Pause
+    Pause,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+    // Waits turn after turn until the player is healed, sees an enemy or gets hurt
+    Rest,
+    PickUp,
+    Descend,
+    Ascend,
+    Look,
+    Inventory
+}
+
+impl Action {
+    fn direction(self) -> Option<Direction> {
+        match self {
+            Action::North => Some(Direction::North),
+            Action::South => Some(Direction::South),
+            Action::East => Some(Direction::East),
+            Action::West => Some(Direction::West),
+            Action::NorthEast => Some(Direction::NorthEast),
+            Action::NorthWest => Some(Direction::NorthWest),
+            Action::SouthEast => Some(Direction::SouthEast),
+            Action::SouthWest => Some(Direction::SouthWest),
+            _ => None
+        }
+    }
 }
 
 fn main() {
-    App::new()
-        .add_plugins((DefaultPlugins.set(ImagePlugin::default_nearest())
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some(WORLDGEN_COMMAND) {
+        worldgen_cli::run(&args[2..]);
+        return;
+    }
+
+    let replay_options = replay_options();
+    let mut requested_seed = match requested_seed() {
+        Ok(requested_seed) => requested_seed,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut map_config = match map_config() {
+        Ok(map_config) => map_config,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    let playback = match &replay_options.replay {
+        Some(path) => match load_replay(path) {
+            Ok(replay) => {
+                // A replay only plays out the same way on the dungeon it was recorded on
+                requested_seed = Some(replay.seed);
+                map_config = replay.map_config;
+                Some(ReplayPlayback::new(replay, replay_options.headless))
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        },
+        None => None
+    };
+
+    let default_plugins = DefaultPlugins.set(ImagePlugin::default_nearest());
+    // Headless replays run without a window or a GPU, as fast as the turns can be taken
+    let default_plugins = if replay_options.headless {
+        default_plugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false
+            })
+            .set(RenderPlugin {
+                render_creation: WgpuSettings { backends: None, ..default() }.into()
+            })
+            .disable::<WinitPlugin>()
+            .add(ScheduleRunnerPlugin::default())
+    } else {
+        default_plugins
+    };
+
+    let mut app = App::new();
+    app
+        .add_plugins((default_plugins
                            , AssetLoadingPlugin::<TileTextureData>::default()
                            , JsonAssetPlugin::<TileTextureData>::new(&["png.json"])
+                           , JsonAssetPlugin::<DungeonProfiles>::new(&["dungeon.json"])
                            , TilemapPlugin
                            , EntropyPlugin::<ChaCha8Rng>::default()
                            , InputManagerPlugin::<Action>::default()
+                           , MenuSystemPlugin::new(MENU_PATH)
+                                .with_renderer(SpriteMenuRenderer::<TileTextureData, RelatedTextureData>::new(TileTextureData::Floor, TileTextureData::Player, 16.0))
         ))
         .add_state::<AppState>()
+        .add_event::<MoveEvent>()
+        .add_event::<TurnTakenEvent>()
+        .add_event::<ActorReadyEvent>()
+        .add_event::<AttackEvent>()
+        .add_event::<DeathEvent>()
+        .add_event::<ExperienceEvent>()
+        .add_event::<LevelUpEvent>()
+        .add_event::<GameOverEvent>()
+        .add_event::<SaveAndQuitEvent>()
+        .add_event::<UseStairsEvent>()
+        .add_event::<LookEvent>()
+        // Any menu being open, the pause menu included, stops time
+        .configure_sets(Update, (TurnSet::Act, TurnSet::PlayerInput, TurnSet::Resolve, TurnSet::Advance).chain().run_if(in_state(AppState::Play)).run_if(not(menu_open)))
         .add_systems(Startup, load_assets)
+        .add_systems(Startup, load_dungeon_profiles)
+        .add_systems(Update, report_profile_changes.run_if(on_event::<AssetEvent<DungeonProfiles>>()))
         .add_systems(Startup, spawn_player)
         .add_systems(Update, folder_loaded.run_if(on_event::<TileAssetLoadedEvent>()))
         .add_systems(Update, load_finished.run_if(on_event::<LoadingFinishedEvent>()))
         .add_systems(OnEnter(AppState::AssetLoaded), setup)
+        .add_systems(Update, wait_for_dungeon_profiles.run_if(in_state(AppState::AssetLoaded)))
         .add_systems(OnEnter(AppState::AssetPrepped), skip_forward)
-        .add_systems(OnEnter(AppState::Generate), generate)
-        .add_systems(Update, play.run_if(in_state(AppState::Play)))
+        .add_systems(OnEnter(AppState::Menu), (finish_recording, leave_run, reset_run).chain())
+        .add_systems(Update, open_main_menu.run_if(in_state(AppState::Menu)).run_if(not(menu_open)))
+        .add_systems(OnExit(AppState::Menu), close_menu)
+        .add_systems(Update, (open_pause_menu.run_if(in_state(AppState::Play)).run_if(not(menu_open)), act_on_menu).chain().after(MenuSet).before(TurnSet::Act))
+        .add_systems(Update, enter_seed.before(MenuSet).run_if(resource_exists::<SeedEntry>()))
+        .add_systems(Update, act_on_controls_menu.after(MenuSet))
+        .add_systems(Update, capture_binding.before(MenuSet).run_if(resource_exists::<Rebinding>()))
+        .add_systems(Update, show_bindings.run_if(resource_exists_and_changed::<Bindings>()))
+        .add_systems(OnEnter(AppState::Continue), load_run)
+        .add_systems(OnEnter(AppState::Generate), (start_run, start_recording, generate).chain())
+        .add_systems(Update, enemy_turns.in_set(TurnSet::Act))
+        .add_systems(Update, finish_playback.in_set(TurnSet::Act).run_if(player_ready).run_if(playback_exhausted))
+        .add_systems(Update, (keep_resting.run_if(resource_exists::<Resting>()), play.run_if(not(resource_exists::<Resting>()))).chain().in_set(TurnSet::PlayerInput).run_if(player_ready))
+        .add_systems(Update, look_around.run_if(on_event::<LookEvent>()))
+        .add_systems(Update, (move_lifeforms, resolve_attacks, reward_experience, track_deaths, remove_dead, gain_experience, take_stairs, game_over).chain().in_set(TurnSet::Resolve))
+        .add_systems(Update, (spend_energy, regenerate, advance_turns).chain().in_set(TurnSet::Advance))
+        .add_systems(Update, update_field_of_view.after(TurnSet::Advance).run_if(in_state(AppState::Play)))
+        .add_systems(OnEnter(AppState::GameOver), (finish_recording, finish_playback.run_if(resource_exists::<ReplayPlayback>()), end_run).chain())
+        .add_systems(OnEnter(AppState::RunSummary), show_run_summary)
+        .add_systems(Update, leave_run_summary.run_if(in_state(AppState::RunSummary)))
+        .add_systems(OnExit(AppState::RunSummary), reset_run)
+        .add_systems(Startup, spawn_seed_text)
+        .add_systems(Update, update_seed_text.run_if(resource_changed::<RunSeed>()))
+        .add_systems(Last, save_on_quit.run_if(in_state(AppState::Play)).run_if(not(resource_exists::<ReplayPlayback>())))
+        .add_systems(Last, finish_recording_on_quit.run_if(in_state(AppState::Play)))
+        .insert_resource(PkvStore::new("exlted", "SamuraiWarriorsDerusted"))
         .insert_resource(TexturesToLoad{indexes: vec![]})
         .insert_resource(TextureArray{textures: vec![]})
-        .run();
-    // KV Store Docs: https://crates.io/crates/bevy_pkv
+        .init_resource::<TurnScheduler>()
+        .init_resource::<LevelingConfig>()
+        .init_resource::<RunStats>()
+        .init_resource::<Dungeon>()
+        .insert_resource(RunSeed{requested: requested_seed, current: None})
+        .insert_resource(map_config)
+        .insert_resource(ReplayRecorder{path: replay_options.record, replay: None});
+
+    if let Some(playback) = playback {
+        app.insert_resource(playback);
+    }
+    app.run();
     // Input Docs: https://crates.io/crates/leafwing-input-manager
 }
 
-fn spawn_player(mut commands: Commands) {
-    commands.spawn(
-        InputManagerBundle::<Action> {
-            action_state: ActionState::default(),
-            input_map: InputMap::new([
-                (QwertyScanCode::W, Action::North),
-                (QwertyScanCode::A, Action::West),
-                (QwertyScanCode::S, Action::South),
-                (QwertyScanCode::D, Action::East),
-                (QwertyScanCode::Space, Action::Skip),
-                (QwertyScanCode::Escape, Action::Pause)
-            ])
-        }
-    ).insert(Player);
-}
-
 fn load_assets(
     mut commands: Commands,
     //server: Res<AssetServer>
@@ -149,20 +305,25 @@ fn load_finished(
     }
 }
 
-fn setup(
-    mut next_state: ResMut<NextState<AppState>>
-) {
-    next_state.set(AppState::AssetPrepped);
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
 }
 
 fn skip_forward(
+    playback: Option<Res<ReplayPlayback>>,
     mut next_state: ResMut<NextState<AppState>>
 ) {
-    next_state.set(AppState::Generate);
+    // Replays start their run straight away, everyone else gets the main menu
+    if playback.is_some() {
+        next_state.set(AppState::Generate);
+    } else {
+        next_state.set(AppState::Menu);
+    }
 }
 
 fn generate_map(
-    map_size: &TilemapSize,
+    map_config: &MapConfig,
+    generator: &GeneratorConfig,
     mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
     tile_data_holder: Res<LoadedAssetData>,
 ) -> WorldState {
@@ -172,33 +333,30 @@ fn generate_map(
         3. Generate Entrance/Exit
         4. Generate Enemies
      */
-    let mut room_generator = RoomGenerator{
-        room_count: 50,
-        map_width: map_size.x as usize,
-        map_height: map_size.y as usize,
-        rooms: vec![],
-        mean_room_width: 7,
-        mean_room_height: 6,
-        width_variance: 3,
-        height_variance: 2,
-        max_enemies_per_room: 2,
-    };
-
-    let (terrain, entities) = room_generator.generate_rooms(&mut rng);
+    let (terrain, spawns, report) = generator.generate_floor(map_config.width as usize, map_config.height as usize, &mut *rng);
+    if !report.is_valid() {
+        warn!("Generated a {} floor that failed validation: {:?}", generator.kind(), report);
+    }
     let mut generated_map = WorldState {
-        terrain,
-        entities
+        terrain: vec![],
+        entities: spawns.into_iter().map(|spawn| Lifeform::from(spawn).into()).collect(),
+        fog_of_war: FogOfWar::new(map_config.width as usize, map_config.height as usize)
     };
 
-    for x in 0..map_size.x {
-        for y in 0..map_size.y {
-            generated_map.terrain[x as usize][y as usize].tile_data = generated_map.terrain[x as usize][y as usize].tile_data.repair_tile_data();
-            generated_map.terrain[x as usize][y as usize].texture = generated_map.terrain[x as usize][y as usize].tile_data.pick_texture(&tile_data_holder, &mut rng);
+    for column in terrain {
+        let mut terrain_column = vec![];
+        for tile_data in column {
+            let tile_data = tile_data.repair_tile_data();
+            terrain_column.push(TerrainData {
+                texture: tile_data.pick_texture(&tile_data_holder, &mut *rng),
+                tile_data
+            });
         }
+        generated_map.terrain.push(terrain_column);
     }
 
     for entity in &mut generated_map.entities {
-        entity.texture.texture = entity.texture.tile_data.pick_texture(&tile_data_holder, &mut rng);
+        entity.lifeform.texture.texture = entity.lifeform.texture.tile_data.pick_texture(&tile_data_holder, &mut *rng);
     }
 
     generated_map
@@ -207,39 +365,69 @@ fn generate_map(
 fn generate(
     mut commands: Commands,
     texture_array: Res<TextureArray>,
-    rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
     tile_data_holder: Res<LoadedAssetData>,
+    run_seed: Res<RunSeed>,
+    map_config: Res<MapConfig>,
+    profiles_handle: Res<DungeonProfilesHandle>,
+    dungeon_profiles: Res<Assets<DungeonProfiles>>,
+    mut dungeon: ResMut<Dungeon>,
     mut next_state: ResMut<NextState<AppState>>
 ) {
-    commands.spawn(Camera2dBundle::default());
-
-    let map_size = TilemapSize {
-        x: MAP_X,
-        y: MAP_Y
+    // Floors the player has already been on come back exactly as they were left
+    let depth = dungeon.depth;
+    let mut map = match dungeon.floors.remove(&depth) {
+        Some(stored_map) => stored_map,
+        None => {
+            if let Some(seed) = run_seed.current {
+                rng.reseed(floor_seed(seed, depth));
+            }
+            // The run keeps the profiles it started with, so editing the file mid-run can't change the floors still to come
+            let generator = dungeon.profiles
+                .get_or_insert_with(|| profiles_handle.profiles(&dungeon_profiles).clone())
+                .generator_for(depth);
+            let mut generated_map = generate_map(&map_config, &generator, rng, tile_data_holder);
+            dungeon.scale_enemies(&mut generated_map);
+            generated_map
+        }
     };
+    dungeon.place_player(&mut map);
+    dungeon.current_terrain = map.terrain.clone();
 
-    // 12/29 -
-    // 1. Save world state somewhere
-    // 2. Allow entities to move
-    // 3. Allow the Player to control themselves
+    let map_size = map.map_size();
+    spawn_floor(&mut commands, map, &map_size, &texture_array);
+    next_state.set(AppState::Play);
+}
 
-    let map = generate_map(&map_size, rng, tile_data_holder);
+fn spawn_floor(
+    commands: &mut Commands,
+    map: WorldState,
+    map_size: &TilemapSize,
+    texture_array: &TextureArray
+) {
+    let map_size = *map_size;
+    commands.insert_resource(map.fog_of_war.clone());
 
     let tilemap_entity = commands.spawn_empty().id();
     let mut tile_storage = TileStorage::empty(map_size);
     for x in 0..map_size.x {
         for y in 0..map_size.y {
             let tile_pos = TilePos { x, y };
-            let tile_entity = commands
+            let mut tile_entity = commands
                 .spawn(TileBundle {
                     position: tile_pos,
                     tilemap_id: TilemapId(tilemap_entity),
                     texture_index: map.terrain[x as usize][y as usize].texture,
+                    visible: TileVisible(false),
                     ..Default::default()
-                })
-                .insert(map.terrain[x as usize][y as usize].tile_data.get_tile_data())
-                .id();
-            tile_storage.set(&tile_pos, tile_entity);
+                });
+            tile_entity.insert(map.terrain[x as usize][y as usize].tile_data.get_tile_data());
+            match map.terrain[x as usize][y as usize].tile_data {
+                TileTextureData::Exit => { tile_entity.insert(DownStairs); }
+                TileTextureData::Entrance => { tile_entity.insert(UpStairs); }
+                _ => {}
+            }
+            tile_storage.set(&tile_pos, tile_entity.id());
         }
     }
 
@@ -256,21 +444,31 @@ fn generate(
         tile_size,
         transform: get_tilemap_center_transform(&map_size, &grid_size, &map_type, 0.0),
         ..Default::default()
-    });
+    }).insert(TerrainLayer);
 
     let tilemap_entity = commands.spawn_empty().id();
     let mut tile_storage = TileStorage::empty(map_size);
-    for entity in map.entities {
-        let tile_entity = commands
+    // Lifeforms coming back from a stored floor or a save carry on their turns where they left off
+    for FloorLifeform { lifeform: entity, energy, ai } in map.entities {
+        let mut tile_entity = commands
             .spawn(TileBundle {
                 position: entity.position,
                 tilemap_id: TilemapId(tilemap_entity),
                 texture_index: entity.texture.texture,
+                visible: TileVisible(false),
                 ..Default::default()
-            })
-            .insert(entity.texture.tile_data.get_tile_data())
-            .id();
-        tile_storage.set(&entity.position, tile_entity);
+            });
+        tile_entity.insert(entity.texture.tile_data.get_tile_data());
+        match entity.texture.tile_data {
+            TileTextureData::Player => { tile_entity.insert((PlayerCharacter, energy.unwrap_or(Energy::ready(DEFAULT_SPEED)))); }
+            _ => {
+                let ai = ai.unwrap_or_else(|| Ai::new(&entity));
+                tile_entity.insert((Enemy, energy.unwrap_or(Energy::new(DEFAULT_SPEED)), ai));
+            }
+        }
+        let position = entity.position;
+        let tile_entity = tile_entity.insert(entity).id();
+        tile_storage.set(&position, tile_entity);
     }
 
     commands.entity(tilemap_entity).insert(TilemapBundle {
@@ -282,23 +480,56 @@ fn generate(
         tile_size,
         transform: get_tilemap_center_transform(&map_size, &grid_size, &map_type, 1.0),
         ..Default::default()
-    });
-    next_state.set(AppState::Play);
+    }).insert(LifeformLayer);
+}
+
+// Pausing opens the pause menu, it never takes a turn
+fn player_action(action_state: &ActionState<Action>) -> Option<Action> {
+    action_state.get_just_pressed().into_iter().find(|key| *key != Action::Pause)
 }
 
-fn play(query: Query<&ActionState<Action>, With<Player>>) {
-    let action_state = query.single();
+fn play(
+    mut commands: Commands,
+    query: Query<&ActionState<Action>, With<Player>>,
+    player_character: Query<(Entity, &Lifeform), With<PlayerCharacter>>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    mut recorder: ResMut<ReplayRecorder>,
+    time: Res<Time>,
+    mut ev_move: EventWriter<MoveEvent>,
+    mut ev_turn_taken: EventWriter<TurnTakenEvent>,
+    mut ev_use_stairs: EventWriter<UseStairsEvent>,
+    mut ev_look: EventWriter<LookEvent>
+) {
+    let Ok((player_entity, player_lifeform)) = player_character.get_single() else {
+        return;
+    };
 
-    let pressed_keys = action_state.get_just_pressed();
+    // Only one turn's worth of input is taken per frame, the scheduler decides when the next one is due
+    let action = match playback {
+        Some(mut playback) => playback.next_action(time.delta()),
+        None => player_action(query.single())
+    };
+    let Some(action) = action else {
+        return;
+    };
+    recorder.record(action);
 
-    for key in pressed_keys {
-        match key {
-            Action::North => {println!("Going North")}
-            Action::South => {println!("Going South")}
-            Action::East => {println!("Going East")}
-            Action::West => {println!("Going West")}
-            Action::Skip => {println!("Skipping Turn")}
-            Action::Pause => {println!("Pausing Game")}
+    if let Some(direction) = action.direction() {
+        ev_move.send(MoveEvent{entity: player_entity, direction});
+        return;
+    }
+    match action {
+        Action::Skip => {ev_turn_taken.send(TurnTakenEvent{entity: player_entity})}
+        Action::Rest => {
+            commands.insert_resource(Resting::new(player_lifeform.health));
+            ev_turn_taken.send(TurnTakenEvent{entity: player_entity});
         }
+        Action::Descend => {ev_use_stairs.send(UseStairsEvent{down: true})}
+        Action::Ascend => {ev_use_stairs.send(UseStairsEvent{down: false})}
+        Action::Look => {ev_look.send(LookEvent)}
+        // There aren't any items in the dungeon yet, so neither of these takes a turn
+        Action::PickUp => {info!("There's nothing here to pick up")}
+        Action::Inventory => {info!("You aren't carrying anything")}
+        _ => {}
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,188 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+use bevy_pkv::PkvStore;
+use leafwing_input_manager::prelude::ActionState;
+use menu_system_plugin::menu::MenuDefinition;
+use menu_system_plugin::navigation::{MenuCancelledEvent, MenuSelectedEvent, MenuState};
+use crate::{Action, AppState};
+use crate::bindings::is_controls_action;
+use crate::game_over::{GameOverEvent, RunOutcome};
+use crate::lifeform::Player;
+use crate::save::{SaveAndQuitEvent, has_saved_run};
+use crate::seed::RunSeed;
+use crate::world::{FloorEntities, despawn_floor};
+
+pub const MENU_PATH: &str = "menus/main.menu.json";
+const MAIN_PAGE: &str = "main";
+const PAUSE_PAGE: &str = "pause";
+const CONTINUE_ACTION: &str = "continue";
+
+// Typing in the seed for the next run, the menu shows it as a prompt until it's confirmed or cancelled
+#[derive(Resource)]
+pub struct SeedEntry {
+    pub digits: String
+}
+
+fn seed_prompt(digits: &str) -> String {
+    format!("Seed for the next run, left empty it's random\n{}_", digits)
+}
+
+fn seed_note(run_seed: &RunSeed) -> Option<String> {
+    run_seed.requested.map(|seed| format!("Seed: {}", seed))
+}
+
+// Runs until the menu has loaded, and again whenever a run is left for the menu
+pub fn open_main_menu(
+    pkv: Res<PkvStore>,
+    run_seed: Res<RunSeed>,
+    definitions: Res<Assets<MenuDefinition>>,
+    mut menu_state: ResMut<MenuState>
+) {
+    let Some(definition) = definitions.get(&menu_state.definition) else {
+        return;
+    };
+    if has_saved_run(&pkv) {
+        menu_state.disabled.remove(CONTINUE_ACTION);
+    } else {
+        menu_state.disabled.insert(CONTINUE_ACTION.to_string());
+    }
+    menu_state.open(MAIN_PAGE, definition);
+    menu_state.note = seed_note(&run_seed);
+}
+
+pub fn close_menu(mut menu_state: ResMut<MenuState>) {
+    menu_state.close();
+}
+
+// Whatever was left of the run the player walked away from
+pub fn leave_run(mut commands: Commands, floor: FloorEntities) {
+    despawn_floor(&mut commands, &floor);
+}
+
+pub fn open_pause_menu(
+    query: Query<&ActionState<Action>, With<Player>>,
+    definitions: Res<Assets<MenuDefinition>>,
+    mut menu_state: ResMut<MenuState>
+) {
+    let Some(definition) = definitions.get(&menu_state.definition) else {
+        return;
+    };
+    if query.single().just_pressed(Action::Pause) {
+        menu_state.open(PAUSE_PAGE, definition);
+    }
+}
+
+// Space and Return pick menu items, but they're bound to actions too, so whatever was pressed to close the menu is used up
+// rather than taking a turn the moment play carries on. Every way of closing the menu goes through here
+fn close_without_acting(menu_state: &mut MenuState, action_state: &mut ActionState<Action>) {
+    menu_state.close();
+    for action in action_state.get_pressed() {
+        action_state.consume(action);
+    }
+}
+
+// Has to run after the pause menu is opened, otherwise the Escape that closes it would open it again straight away
+pub fn act_on_menu(
+    mut commands: Commands,
+    mut ev_selected: EventReader<MenuSelectedEvent>,
+    mut ev_cancelled: EventReader<MenuCancelledEvent>,
+    mut menu_state: ResMut<MenuState>,
+    mut player: Query<&mut ActionState<Action>, With<Player>>,
+    run_seed: Res<RunSeed>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut ev_save_and_quit: EventWriter<SaveAndQuitEvent>,
+    mut ev_game_over: EventWriter<GameOverEvent>,
+    mut ev_app_exit: EventWriter<AppExit>
+) {
+    for ev in ev_selected.read() {
+        match ev.action.as_str() {
+            "new_run" => next_state.set(AppState::Generate),
+            CONTINUE_ACTION => next_state.set(AppState::Continue),
+            "seed" => {
+                let digits = run_seed.requested.map_or(String::new(), |seed| seed.to_string());
+                menu_state.prompt = Some(seed_prompt(&digits));
+                commands.insert_resource(SeedEntry { digits });
+            }
+            "quit" => ev_app_exit.send(AppExit),
+            "resume" => close_without_acting(&mut menu_state, &mut player.single_mut()),
+            "save_and_quit" => {
+                ev_save_and_quit.send(SaveAndQuitEvent);
+                close_without_acting(&mut menu_state, &mut player.single_mut());
+                next_state.set(AppState::Menu);
+            }
+            "abandon_run" => {
+                close_without_acting(&mut menu_state, &mut player.single_mut());
+                ev_game_over.send(GameOverEvent { outcome: RunOutcome::Lost("Abandoned the run".to_string()) });
+            }
+            // The controls menu is looked after by the bindings
+            action if is_controls_action(action) => {}
+            action => warn!("Nothing is done for the menu action {}", action)
+        }
+    }
+
+    // Backing out of the pause menu carries on playing, backing out of the main menu does nothing
+    for ev in ev_cancelled.read() {
+        if ev.page == PAUSE_PAGE {
+            close_without_acting(&mut menu_state, &mut player.single_mut());
+        }
+    }
+}
+
+// Has to run before the menu's own navigation, so the key that finishes typing isn't also used to pick an item
+pub fn enter_seed(
+    mut commands: Commands,
+    mut seed_entry: ResMut<SeedEntry>,
+    mut ev_character: EventReader<ReceivedCharacter>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut run_seed: ResMut<RunSeed>,
+    mut menu_state: ResMut<MenuState>
+) {
+    let typed = seed_entry.digits.clone();
+    for ev in ev_character.read() {
+        let digits = format!("{}{}", seed_entry.digits, ev.char);
+        if ev.char.is_ascii_digit() && digits.parse::<u64>().is_ok() {
+            seed_entry.digits = digits;
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        seed_entry.digits.pop();
+    }
+    if seed_entry.digits != typed {
+        menu_state.prompt = Some(seed_prompt(&seed_entry.digits));
+    }
+
+    let confirmed = keys.just_pressed(KeyCode::Return);
+    if !confirmed && !keys.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    if confirmed {
+        // Left empty, every new run gets a random seed again
+        run_seed.requested = seed_entry.digits.parse().ok();
+    }
+    keys.clear_just_pressed(KeyCode::Return);
+    keys.clear_just_pressed(KeyCode::Escape);
+    menu_state.prompt = None;
+    menu_state.note = seed_note(&run_seed);
+    commands.remove_resource::<SeedEntry>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player_action;
+
+    #[test]
+    fn resuming_does_not_take_a_turn() {
+        let mut menu_state = MenuState::default();
+        menu_state.pages.push(PAUSE_PAGE.to_string());
+        // Space both picks Resume and skips a turn
+        let mut action_state = ActionState::<Action>::default();
+        action_state.press(Action::Skip);
+        assert_eq!(player_action(&action_state), Some(Action::Skip));
+
+        close_without_acting(&mut menu_state, &mut action_state);
+        assert!(!menu_state.is_open());
+        assert_eq!(player_action(&action_state), None);
+    }
+}
@@ -0,0 +1,344 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use crate::tile_data::TerrainData;
+
+// Grids are indexed [x][y] like the terrain, points are (x, y)
+pub type Point = (usize, usize);
+
+pub const UNREACHABLE: u32 = u32::MAX;
+
+// Straight steps come first, so when a diagonal is no better the straight step is taken
+const OFFSETS: [(isize, isize); 8] = [(0, 1), (0, -1), (1, 0), (-1, 0), (1, 1), (-1, 1), (1, -1), (-1, -1)];
+
+#[derive(Clone, Debug)]
+pub struct PathGrid {
+    width: usize,
+    height: usize,
+    passable: Vec<bool>,
+    // Dynamic blockers, i.e. lifeforms, which can change every turn
+    blocked: Vec<bool>
+}
+
+impl PathGrid {
+    pub fn new(width: usize, height: usize) -> PathGrid {
+        PathGrid {
+            width,
+            height,
+            passable: vec![false; width * height],
+            blocked: vec![false; width * height]
+        }
+    }
+
+    pub fn from_terrain(terrain: &[Vec<TerrainData>]) -> PathGrid {
+        let width = terrain.len();
+        let height = if width > 0 { terrain[0].len() } else { 0 };
+        let mut grid = PathGrid::new(width, height);
+        for (x, column) in terrain.iter().enumerate() {
+            for (y, terrain_data) in column.iter().enumerate() {
+                grid.set_passable((x, y), terrain_data.tile_data.is_passable());
+            }
+        }
+        grid
+    }
+
+    fn index(&self, point: Point) -> Option<usize> {
+        if point.0 < self.width && point.1 < self.height {
+            Some(point.0 * self.height + point.1)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_passable(&mut self, point: Point, passable: bool) {
+        if let Some(idx) = self.index(point) {
+            self.passable[idx] = passable;
+        }
+    }
+
+    pub fn is_passable(&self, point: Point) -> bool {
+        match self.index(point) {
+            Some(idx) => self.passable[idx],
+            None => false
+        }
+    }
+
+    pub fn set_blocked(&mut self, point: Point, blocked: bool) {
+        if let Some(idx) = self.index(point) {
+            self.blocked[idx] = blocked;
+        }
+    }
+
+    pub fn is_blocked(&self, point: Point) -> bool {
+        match self.index(point) {
+            Some(idx) => self.blocked[idx],
+            None => false
+        }
+    }
+
+    // Passable terrain with nothing standing on it
+    pub fn is_walkable(&self, point: Point) -> bool {
+        self.is_passable(point) && !self.is_blocked(point)
+    }
+
+    fn offset(&self, point: Point, offset_x: isize, offset_y: isize) -> Option<Point> {
+        let next = (point.0.checked_add_signed(offset_x)?, point.1.checked_add_signed(offset_y)?);
+        self.index(next).map(|_| next)
+    }
+
+    // Diagonals can't squeeze past the corner of a wall, the same as the player, so both tiles either side have to be open
+    pub fn neighbors(&self, point: Point) -> Vec<Point> {
+        OFFSETS.into_iter()
+            .filter(|(offset_x, offset_y)| *offset_x == 0 || *offset_y == 0 || [(*offset_x, 0), (0, *offset_y)].into_iter()
+                .all(|(side_x, side_y)| self.offset(point, side_x, side_y).is_some_and(|side| self.is_passable(side))))
+            .filter_map(|(offset_x, offset_y)| self.offset(point, offset_x, offset_y))
+            .collect()
+    }
+}
+
+// Diagonal steps cost the same as straight ones, so this is how many steps apart two points are in the open
+fn chebyshev(from: Point, to: Point) -> u32 {
+    from.0.abs_diff(to.0).max(from.1.abs_diff(to.1)) as u32
+}
+
+// A* from start to goal over walkable tiles, the goal itself may be blocked so lifeforms can path into each other to attack
+// Returns the path excluding the start and including the goal
+pub fn find_path(grid: &PathGrid, start: Point, goal: Point) -> Option<Vec<Point>> {
+    let (Some(start_idx), Some(goal_idx)) = (grid.index(start), grid.index(goal)) else {
+        return None;
+    };
+    if start == goal {
+        return Some(vec![]);
+    }
+    if !grid.is_passable(goal) {
+        return None;
+    }
+
+    let mut cost = vec![UNREACHABLE; grid.width * grid.height];
+    let mut came_from: Vec<Option<Point>> = vec![None; grid.width * grid.height];
+    let mut open = BinaryHeap::new();
+    cost[start_idx] = 0;
+    open.push(Reverse((chebyshev(start, goal), 0, start)));
+
+    while let Some(Reverse((_, current_cost, current))) = open.pop() {
+        if current == goal {
+            break;
+        }
+        if current_cost > cost[grid.index(current).unwrap()] {
+            continue;
+        }
+        for next in grid.neighbors(current) {
+            if next != goal && !grid.is_walkable(next) {
+                continue;
+            }
+            let next_idx = grid.index(next).unwrap();
+            let next_cost = current_cost + 1;
+            if next_cost < cost[next_idx] {
+                cost[next_idx] = next_cost;
+                came_from[next_idx] = Some(current);
+                open.push(Reverse((next_cost + chebyshev(next, goal), next_cost, next)));
+            }
+        }
+    }
+
+    if cost[goal_idx] == UNREACHABLE {
+        return None;
+    }
+
+    let mut path = vec![goal];
+    let mut current = goal;
+    while let Some(previous) = came_from[grid.index(current).unwrap()] {
+        if previous == start {
+            break;
+        }
+        path.push(previous);
+        current = previous;
+    }
+    path.reverse();
+    Some(path)
+}
+
+// Distance from every tile to the nearest goal, shared by everything heading towards (or away from) the same goals
+#[derive(Clone, Debug)]
+pub struct DijkstraMap {
+    width: usize,
+    height: usize,
+    distances: Vec<u32>
+}
+
+impl DijkstraMap {
+    // Only terrain is considered, lifeforms get in the way of each other when stepping instead
+    pub fn new(grid: &PathGrid, goals: &[Point]) -> DijkstraMap {
+        let mut distances = vec![UNREACHABLE; grid.width * grid.height];
+        let mut frontier = VecDeque::new();
+        for goal in goals {
+            if let Some(idx) = grid.index(*goal) {
+                if distances[idx] != 0 {
+                    distances[idx] = 0;
+                    frontier.push_back(*goal);
+                }
+            }
+        }
+
+        // Every step costs the same, so a breadth first fill is all Dijkstra needs to be
+        while let Some(current) = frontier.pop_front() {
+            let current_distance = distances[grid.index(current).unwrap()];
+            for next in grid.neighbors(current) {
+                let next_idx = grid.index(next).unwrap();
+                if grid.is_passable(next) && distances[next_idx] == UNREACHABLE {
+                    distances[next_idx] = current_distance + 1;
+                    frontier.push_back(next);
+                }
+            }
+        }
+
+        DijkstraMap {
+            width: grid.width,
+            height: grid.height,
+            distances
+        }
+    }
+
+    pub fn distance(&self, point: Point) -> Option<u32> {
+        if point.0 >= self.width || point.1 >= self.height {
+            return None;
+        }
+        match self.distances[point.0 * self.height + point.1] {
+            UNREACHABLE => None,
+            distance => Some(distance)
+        }
+    }
+
+    // The neighbor that gets closest to a goal, goals may be stepped onto even when blocked
+    pub fn step_towards(&self, grid: &PathGrid, from: Point) -> Option<Point> {
+        let mut best = self.distance(from)?;
+        let mut output = None;
+        for next in grid.neighbors(from) {
+            let Some(next_distance) = self.distance(next) else {
+                continue;
+            };
+            if next_distance < best && (next_distance == 0 || grid.is_walkable(next)) {
+                best = next_distance;
+                output = Some(next);
+            }
+        }
+        output
+    }
+
+    // The neighbor that gets furthest from every goal
+    pub fn step_away(&self, grid: &PathGrid, from: Point) -> Option<Point> {
+        let mut best = self.distance(from)?;
+        let mut output = None;
+        for next in grid.neighbors(from) {
+            let Some(next_distance) = self.distance(next) else {
+                continue;
+            };
+            if next_distance > best && grid.is_walkable(next) {
+                best = next_distance;
+                output = Some(next);
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // '#' is a wall, 'x' is a blocker standing on a floor, anything else is floor
+    // Rows are listed top to bottom, so the last row is y = 0
+    fn grid_from(rows: &[&str]) -> PathGrid {
+        let height = rows.len();
+        let width = rows[0].len();
+        let mut grid = PathGrid::new(width, height);
+        for (row_idx, row) in rows.iter().enumerate() {
+            let y = height - 1 - row_idx;
+            for (x, tile) in row.chars().enumerate() {
+                grid.set_passable((x, y), tile != '#');
+                grid.set_blocked((x, y), tile == 'x');
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn path_goes_around_walls() {
+        let grid = grid_from(&[
+            ".....",
+            ".###.",
+            "....."
+        ]);
+        let path = find_path(&grid, (0, 1), (4, 1)).unwrap();
+        assert_eq!(path.len(), 6);
+        assert_eq!(*path.last().unwrap(), (4, 1));
+        assert!(path.iter().all(|point| grid.is_passable(*point)));
+    }
+
+    #[test]
+    fn path_avoids_blockers_but_may_end_on_one() {
+        let grid = grid_from(&[
+            "...",
+            ".xx",
+            "..."
+        ]);
+        assert_eq!(find_path(&grid, (0, 1), (2, 1)).unwrap().len(), 2);
+        assert_eq!(find_path(&grid, (1, 0), (1, 1)).unwrap(), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn no_path_through_solid_walls() {
+        let grid = grid_from(&[
+            "..#..",
+            "..#..",
+            "..#.."
+        ]);
+        assert_eq!(find_path(&grid, (0, 0), (4, 0)), None);
+    }
+
+    #[test]
+    fn dijkstra_map_counts_steps_from_goal() {
+        let grid = grid_from(&[
+            "....",
+            ".##.",
+            "...."
+        ]);
+        let map = DijkstraMap::new(&grid, &[(0, 0)]);
+        assert_eq!(map.distance((0, 0)), Some(0));
+        assert_eq!(map.distance((3, 2)), Some(5));
+        assert_eq!(map.distance((1, 1)), None);
+        assert_eq!(map.step_towards(&grid, (3, 0)), Some((2, 0)));
+        assert_eq!(map.step_away(&grid, (1, 0)), Some((2, 0)));
+    }
+
+    #[test]
+    fn dijkstra_map_steps_around_blockers() {
+        let grid = grid_from(&[
+            "...",
+            "x..",
+            "..."
+        ]);
+        let map = DijkstraMap::new(&grid, &[(0, 0)]);
+        assert_eq!(map.step_towards(&grid, (1, 1)), Some((0, 0)));
+        assert_eq!(map.step_towards(&grid, (0, 2)), Some((1, 1)));
+        assert_eq!(map.step_towards(&grid, (1, 0)), Some((0, 0)));
+    }
+
+    #[test]
+    fn diagonal_steps_never_cut_wall_corners() {
+        let open = grid_from(&[
+            "..",
+            ".."
+        ]);
+        assert_eq!(find_path(&open, (0, 1), (1, 0)), Some(vec![(1, 0)]));
+        assert_eq!(open.neighbors((0, 0)), vec![(0, 1), (1, 0), (1, 1)]);
+
+        let cornered = grid_from(&[
+            "..",
+            "#."
+        ]);
+        assert!(!cornered.neighbors((0, 1)).contains(&(1, 0)));
+        assert_eq!(find_path(&cornered, (0, 1), (1, 0)), Some(vec![(1, 1), (1, 0)]));
+        let map = DijkstraMap::new(&cornered, &[(1, 0)]);
+        assert_eq!(map.distance((0, 1)), Some(2));
+    }
+}
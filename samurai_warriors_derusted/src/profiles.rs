@@ -0,0 +1,56 @@
+use bevy::app::AppExit;
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use worldgen::DungeonProfiles;
+use crate::AppState;
+
+pub const DUNGEON_PROFILES_PATH: &str = "dungeon/generation.dungeon.json";
+
+// Held for the whole game so the asset stays loaded, and edits to the file are picked up by the next run started
+#[derive(Resource, Default)]
+pub struct DungeonProfilesHandle(pub Handle<DungeonProfiles>);
+
+impl DungeonProfilesHandle {
+    // Nothing is generated before wait_for_dungeon_profiles has seen the file load, so there's no stand in to fall back on
+    pub fn profiles<'a>(&self, assets: &'a Assets<DungeonProfiles>) -> &'a DungeonProfiles {
+        assets.get(&self.0).expect("Dungeon profiles should be loaded before any floor is generated")
+    }
+}
+
+pub fn load_dungeon_profiles(mut commands: Commands, server: Res<AssetServer>) {
+    commands.insert_resource(DungeonProfilesHandle(server.load(DUNGEON_PROFILES_PATH)));
+}
+
+// The textures are in by now, but the same seed only gives the same dungeon once the profiles are too
+pub fn wait_for_dungeon_profiles(
+    server: Res<AssetServer>,
+    handle: Res<DungeonProfilesHandle>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut ev_app_exit: EventWriter<AppExit>
+) {
+    match server.get_load_state(&handle.0) {
+        Some(LoadState::Loaded) => next_state.set(AppState::AssetPrepped),
+        Some(LoadState::Failed) => {
+            error!("Couldn't load the dungeon profiles from {}", DUNGEON_PROFILES_PATH);
+            ev_app_exit.send(AppExit);
+        }
+        _ => {}
+    }
+}
+
+pub fn report_profile_changes(
+    mut events: EventReader<AssetEvent<DungeonProfiles>>,
+    handle: Res<DungeonProfilesHandle>
+) {
+    for event in events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } if *id == handle.0.id() => {
+                info!("Loaded dungeon profiles from {}", DUNGEON_PROFILES_PATH);
+            }
+            AssetEvent::Modified { id } if *id == handle.0.id() => {
+                info!("Reloaded dungeon profiles, runs started from now on will use them");
+            }
+            _ => {}
+        }
+    }
+}
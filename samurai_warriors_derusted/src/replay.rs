@@ -0,0 +1,401 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use bevy::app::AppExit;
+use bevy::ecs::query::Has;
+use bevy::prelude::*;
+use bevy::window::WindowCloseRequested;
+use bevy_ecs_tilemap::prelude::TilePos;
+use crate::Action;
+use crate::game_over::RunStats;
+use crate::lifeform::{Lifeform, PlayerCharacter};
+use crate::seed::RunSeed;
+use crate::turn::TurnScheduler;
+use crate::utils::arg_value;
+use crate::world::{Dungeon, MapConfig};
+
+pub const RECORD_ARG: &str = "--record";
+pub const REPLAY_ARG: &str = "--replay";
+pub const HEADLESS_ARG: &str = "--headless";
+
+// How long a visual replay waits between turns, headless replays don't wait at all
+const VISUAL_STEP_SECONDS: f32 = 0.15;
+
+// Enough of the world at the end of a run to tell whether a replay played out the same way
+#[derive(serde::Serialize, serde::Deserialize, Eq, PartialEq, Clone, Debug)]
+pub struct FinalState {
+    pub depth: u32,
+    pub turns: u64,
+    pub kills: u32,
+    // (x, y, health, is_player) of everything still alive on the final floor, sorted by position
+    pub lifeforms: Vec<(u32, u32, u32, bool)>
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    // Recordings from before maps could be resized were all made at the default size
+    #[serde(default)]
+    pub map_config: MapConfig,
+    // Every action the player took, one per turn
+    pub actions: Vec<Action>,
+    pub final_state: Option<FinalState>
+}
+
+impl Replay {
+    // Recordings without a final state have nothing to be compared against
+    pub fn matches(&self, final_state: &FinalState) -> bool {
+        self.final_state.as_ref().is_none_or(|expected| expected == final_state)
+    }
+}
+
+#[derive(Default)]
+pub struct ReplayOptions {
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub headless: bool
+}
+
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+    pub path: Option<PathBuf>,
+    pub replay: Option<Replay>
+}
+
+impl ReplayRecorder {
+    pub fn record(&mut self, action: Action) {
+        if let Some(replay) = &mut self.replay {
+            replay.actions.push(action);
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub headless: bool,
+    next_action: usize,
+    step: Timer
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay, headless: bool) -> ReplayPlayback {
+        let step_seconds = if headless { 0.0 } else { VISUAL_STEP_SECONDS };
+        ReplayPlayback {
+            replay,
+            headless,
+            next_action: 0,
+            step: Timer::from_seconds(step_seconds, TimerMode::Repeating)
+        }
+    }
+
+    // The next recorded action, once enough time has passed since the last one
+    pub fn next_action(&mut self, delta: Duration) -> Option<Action> {
+        if !self.headless && !self.step.tick(delta).just_finished() {
+            return None;
+        }
+        let action = self.replay.actions.get(self.next_action).copied();
+        if action.is_some() {
+            self.next_action += 1;
+        }
+        action
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_action >= self.replay.actions.len()
+    }
+}
+
+pub fn parse_replay_options(args: &[String]) -> ReplayOptions {
+    ReplayOptions {
+        record: arg_value(args, RECORD_ARG).map(PathBuf::from),
+        replay: arg_value(args, REPLAY_ARG).map(PathBuf::from),
+        headless: args.iter().any(|arg| arg == HEADLESS_ARG)
+    }
+}
+
+pub fn replay_options() -> ReplayOptions {
+    let args: Vec<String> = std::env::args().collect();
+    parse_replay_options(&args)
+}
+
+pub fn load_replay(path: &Path) -> Result<Replay, String> {
+    let contents = std::fs::read_to_string(path).map_err(|error| format!("Couldn't read replay {}: {}", path.display(), error))?;
+    serde_json::from_str(&contents).map_err(|error| format!("Couldn't parse replay {}: {}", path.display(), error))
+}
+
+pub fn write_replay(path: &Path, replay: &Replay) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(replay).map_err(|error| format!("Couldn't serialize replay: {}", error))?;
+    std::fs::write(path, contents).map_err(|error| format!("Couldn't write replay {}: {}", path.display(), error))
+}
+
+pub fn capture_final_state<'a>(
+    dungeon: &Dungeon,
+    scheduler: &TurnScheduler,
+    run_stats: &RunStats,
+    lifeforms: impl IntoIterator<Item = (&'a TilePos, &'a Lifeform, bool)>
+) -> FinalState {
+    let mut remaining: Vec<(u32, u32, u32, bool)> = lifeforms.into_iter()
+        .map(|(tile_pos, lifeform, is_player)| (tile_pos.x, tile_pos.y, lifeform.health, is_player))
+        .collect();
+    remaining.sort();
+
+    FinalState {
+        depth: dungeon.depth,
+        turns: scheduler.turns,
+        kills: run_stats.kills,
+        lifeforms: remaining
+    }
+}
+
+// Starts a fresh recording at the beginning of each new run when one was asked for
+pub fn start_recording(
+    run_seed: Res<RunSeed>,
+    map_config: Res<MapConfig>,
+    mut recorder: ResMut<ReplayRecorder>
+) {
+    let Some(seed) = run_seed.current else {
+        return;
+    };
+    if recorder.path.is_some() && recorder.replay.is_none() {
+        recorder.replay = Some(Replay {
+            seed,
+            map_config: *map_config,
+            actions: vec![],
+            final_state: None
+        });
+    }
+}
+
+pub fn finish_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    dungeon: Res<Dungeon>,
+    scheduler: Res<TurnScheduler>,
+    run_stats: Res<RunStats>,
+    lifeforms: Query<(&TilePos, &Lifeform, Has<PlayerCharacter>)>
+) {
+    let (Some(path), Some(mut replay)) = (recorder.path.clone(), recorder.replay.take()) else {
+        return;
+    };
+    replay.final_state = Some(capture_final_state(&dungeon, &scheduler, &run_stats, &lifeforms));
+    match write_replay(&path, &replay) {
+        Ok(()) => info!("Recorded {} turns to {}", replay.actions.len(), path.display()),
+        Err(error) => warn!("{}", error)
+    }
+}
+
+// Quitting part way through a run still keeps the recording of it
+pub fn finish_recording_on_quit(
+    ev_close_requested: EventReader<WindowCloseRequested>,
+    ev_app_exit: EventReader<AppExit>,
+    recorder: ResMut<ReplayRecorder>,
+    dungeon: Res<Dungeon>,
+    scheduler: Res<TurnScheduler>,
+    run_stats: Res<RunStats>,
+    lifeforms: Query<(&TilePos, &Lifeform, Has<PlayerCharacter>)>
+) {
+    if ev_close_requested.is_empty() && ev_app_exit.is_empty() {
+        return;
+    }
+    finish_recording(recorder, dungeon, scheduler, run_stats, lifeforms);
+}
+
+// Runs once the replay has no more actions to give, or the run it's replaying ends
+pub fn finish_playback(
+    mut commands: Commands,
+    playback: Res<ReplayPlayback>,
+    dungeon: Res<Dungeon>,
+    scheduler: Res<TurnScheduler>,
+    run_stats: Res<RunStats>,
+    lifeforms: Query<(&TilePos, &Lifeform, Has<PlayerCharacter>)>,
+    mut ev_app_exit: EventWriter<AppExit>
+) {
+    let final_state = capture_final_state(&dungeon, &scheduler, &run_stats, &lifeforms);
+    let matches = playback.replay.matches(&final_state);
+    match &playback.replay.final_state {
+        Some(_) if matches => info!("Replay finished and matches the recording"),
+        Some(expected) => error!("Replay diverged from the recording\nExpected: {:?}\nActual: {:?}", expected, final_state),
+        None => info!("Replay finished: {:?}", final_state)
+    }
+
+    if playback.headless {
+        if !matches {
+            std::process::exit(1);
+        }
+        ev_app_exit.send(AppExit);
+    } else {
+        // Hand control back to the keyboard once a visual replay is done
+        commands.remove_resource::<ReplayPlayback>();
+    }
+}
+
+pub fn playback_exhausted(playback: Option<Res<ReplayPlayback>>) -> bool {
+    match playback {
+        Some(playback) => playback.is_finished(),
+        None => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs_tilemap::prelude::TileTextureIndex;
+    use bevy_prng::ChaCha8Rng;
+    use bevy_rand::prelude::*;
+    use worldgen::{LifeformSpawn, TileTextureData, floor_seed};
+    use crate::{play, spawn_floor};
+    use crate::ai::enemy_turns;
+    use crate::combat::{AttackEvent, DeathEvent, remove_dead, resolve_attacks};
+    use crate::fov::{FogOfWar, LookEvent};
+    use crate::game_over::{GameOverEvent, track_deaths};
+    use crate::lifeform::{MoveEvent, move_lifeforms};
+    use crate::tile_data::{FloorLifeform, TerrainData, TextureArray, WorldState};
+    use crate::turn::{ActorReadyEvent, TurnSet, TurnTakenEvent, advance_turns, player_ready, regenerate, spend_energy};
+    use crate::world::UseStairsEvent;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn replay() -> Replay {
+        Replay {
+            seed: 42,
+            map_config: MapConfig { width: 300, height: 200 },
+            actions: vec![Action::North, Action::East, Action::Skip, Action::West],
+            final_state: Some(FinalState {
+                depth: 2,
+                turns: 4,
+                kills: 1,
+                lifeforms: vec![(3, 4, 20, true), (10, 2, 5, false)]
+            })
+        }
+    }
+
+    // A walled in room with the player at one end and an enemy at the other
+    fn arena() -> WorldState {
+        let (width, height) = (9, 5);
+        let terrain = (0..width).map(|x| (0..height).map(|y| TerrainData {
+            texture: TileTextureIndex(0),
+            tile_data: if x == 0 || y == 0 || x == width - 1 || y == height - 1 { TileTextureData::None } else { TileTextureData::Floor }
+        }).collect()).collect();
+        // The player can't die in a handful of turns, so every action is taken
+        let lifeform = |x: usize, y: usize, tile_data: TileTextureData, health: u32| -> FloorLifeform {
+            Lifeform::from(LifeformSpawn { tile_data, x, y, health, strength: 6, defense: 1, level: 0, experience: 1 }).into()
+        };
+        WorldState {
+            terrain,
+            entities: vec![lifeform(2, 2, TileTextureData::Player, 100), lifeform(6, 2, TileTextureData::Enemy, 20)],
+            fog_of_war: FogOfWar::new(width, height)
+        }
+    }
+
+    // Plays the actions out on the arena with the game's own turn, movement and combat systems, recording them as it goes
+    fn play_out(seed: u64, actions: Vec<Action>) -> Replay {
+        let turns = actions.len();
+        let floor = arena();
+        let recording = |actions| Replay { seed, map_config: MapConfig::default(), actions, final_state: None };
+
+        let mut app = App::new();
+        app
+            .add_plugins(EntropyPlugin::<ChaCha8Rng>::default())
+            .add_event::<MoveEvent>()
+            .add_event::<TurnTakenEvent>()
+            .add_event::<ActorReadyEvent>()
+            .add_event::<AttackEvent>()
+            .add_event::<DeathEvent>()
+            .add_event::<GameOverEvent>()
+            .add_event::<UseStairsEvent>()
+            .add_event::<LookEvent>()
+            .init_resource::<Time>()
+            .init_resource::<TurnScheduler>()
+            .init_resource::<RunStats>()
+            .insert_resource(Dungeon { current_terrain: floor.terrain.clone(), ..Dungeon::default() })
+            .insert_resource(ReplayPlayback::new(recording(actions), true))
+            .insert_resource(ReplayRecorder { path: None, replay: Some(recording(vec![])) })
+            .configure_sets(Update, (TurnSet::Act, TurnSet::PlayerInput, TurnSet::Resolve, TurnSet::Advance).chain())
+            .add_systems(Update, enemy_turns.in_set(TurnSet::Act))
+            .add_systems(Update, play.in_set(TurnSet::PlayerInput).run_if(player_ready))
+            .add_systems(Update, (move_lifeforms, resolve_attacks, track_deaths, remove_dead).chain().in_set(TurnSet::Resolve))
+            .add_systems(Update, (spend_energy, regenerate, advance_turns).chain().in_set(TurnSet::Advance));
+
+        let map_size = floor.map_size();
+        let mut floor = Some(floor);
+        app.add_systems(Startup, move |mut commands: Commands| {
+            if let Some(floor) = floor.take() {
+                spawn_floor(&mut commands, floor, &map_size, &TextureArray { textures: vec![] });
+            }
+        });
+        app.world.resource_mut::<GlobalEntropy<ChaCha8Rng>>().reseed(floor_seed(seed, 1));
+
+        // Every update is one player turn, the first also spawns the floor
+        for _ in 0..turns {
+            app.update();
+        }
+
+        let mut lifeforms = app.world.query::<(&TilePos, &Lifeform, Has<PlayerCharacter>)>();
+        let final_state = capture_final_state(
+            app.world.resource::<Dungeon>(),
+            app.world.resource::<TurnScheduler>(),
+            app.world.resource::<RunStats>(),
+            lifeforms.iter(&app.world)
+        );
+        let mut replay = app.world.resource_mut::<ReplayRecorder>().replay.take().unwrap();
+        replay.final_state = Some(final_state);
+        replay
+    }
+
+    #[test]
+    fn replaying_a_recording_plays_out_the_same_way() {
+        let actions = vec![Action::East, Action::East, Action::East, Action::Skip, Action::East, Action::Skip];
+        let recorded = play_out(42, actions.clone());
+        assert_eq!(recorded.actions, actions);
+        assert_eq!(recorded.final_state.as_ref().unwrap().turns, actions.len() as u64);
+
+        let replayed = play_out(recorded.seed, recorded.actions.clone());
+        assert!(recorded.matches(replayed.final_state.as_ref().unwrap()));
+
+        // Heading the other way can't end up with everyone in the same place
+        let tampered = play_out(recorded.seed, vec![Action::West; actions.len()]);
+        assert!(!recorded.matches(tampered.final_state.as_ref().unwrap()));
+    }
+
+    #[test]
+    fn options_come_from_args() {
+        let options = parse_replay_options(&args(&["game", "--replay", "run.json", "--headless"]));
+        assert_eq!(options.replay, Some(PathBuf::from("run.json")));
+        assert_eq!(options.record, None);
+        assert!(options.headless);
+
+        let options = parse_replay_options(&args(&["game", "--record=run.json"]));
+        assert_eq!(options.record, Some(PathBuf::from("run.json")));
+        assert!(!options.headless);
+    }
+
+    #[test]
+    fn replays_survive_a_round_trip() {
+        let replay = replay();
+        let loaded: Replay = serde_json::from_str(&serde_json::to_string(&replay).unwrap()).unwrap();
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.map_config, replay.map_config);
+        assert_eq!(loaded.actions, replay.actions);
+        assert_eq!(loaded.final_state, replay.final_state);
+    }
+
+    #[test]
+    fn headless_playback_hands_out_every_action_in_order() {
+        let mut playback = ReplayPlayback::new(replay(), true);
+        let mut actions = vec![];
+        while let Some(action) = playback.next_action(Duration::ZERO) {
+            actions.push(action);
+        }
+        assert_eq!(actions, replay().actions);
+        assert!(playback.is_finished());
+    }
+
+    #[test]
+    fn visual_playback_waits_between_actions() {
+        let mut playback = ReplayPlayback::new(replay(), false);
+        assert_eq!(playback.next_action(Duration::from_millis(10)), None);
+        assert_eq!(playback.next_action(Duration::from_secs_f32(VISUAL_STEP_SECONDS)), Some(Action::North));
+        assert!(!playback.is_finished());
+    }
+}
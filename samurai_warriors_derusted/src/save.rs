@@ -0,0 +1,318 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::WindowCloseRequested;
+use bevy_ecs_tilemap::prelude::{TilePos, TileTextureIndex};
+use bevy_pkv::PkvStore;
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::GlobalEntropy;
+use rand_core::RngCore;
+use worldgen::DungeonProfiles;
+use crate::{AppState, spawn_floor};
+use crate::ai::{Ai, AiState};
+use crate::fov::{FogOfWar, TileVisibility};
+use crate::game_over::RunStats;
+use crate::lifeform::Lifeform;
+use crate::seed::RunSeed;
+use crate::tile_data::{FloorLifeform, TerrainData, TextureArray, TileTextureData, WorldState};
+use crate::turn::{Energy, TurnScheduler};
+use crate::world::Dungeon;
+
+// A run is a single save slot, it's emptied as soon as it's loaded or the run ends
+const SAVE_KEY: &str = "saved_run";
+
+// Save & Quit was picked from the pause menu
+#[derive(Event)]
+pub struct SaveAndQuitEvent;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedTerrain {
+    texture: u32,
+    tile_data: TileTextureData
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedLifeform {
+    texture: u32,
+    tile_data: TileTextureData,
+    x: u32,
+    y: u32,
+    health: u32,
+    // Saves from before health could regenerate don't have it, so it's taken to be the health they were saved with
+    #[serde(default)]
+    max_health: u32,
+    strength: u32,
+    defense: u32,
+    level: u32,
+    experience: u32,
+    energy: Option<Energy>,
+    ai: Option<SavedAi>
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedAi {
+    state: AiState,
+    home_x: u32,
+    home_y: u32,
+    wander_radius: u32,
+    sight_range: u32,
+    flee_percent: u32
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedFloor {
+    depth: u32,
+    terrain: Vec<Vec<SavedTerrain>>,
+    entities: Vec<SavedLifeform>,
+    fog_width: usize,
+    fog_height: usize,
+    fog_of_war: Vec<TileVisibility>
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SavedRun {
+    depth: u32,
+    arrived_from_below: bool,
+    current_floor: SavedFloor,
+    other_floors: Vec<SavedFloor>,
+    ticks: u64,
+    turns: u64,
+    kills: u32,
+    deepest_floor: u32,
+    seed: u64,
+    // The run's random number generator is reseeded with this when saving and loading, so both carry on identically
+    rng_seed: [u8; 32],
+    profiles: Option<DungeonProfiles>
+}
+
+impl SavedAi {
+    fn from_ai(ai: &Ai) -> SavedAi {
+        SavedAi {
+            state: ai.state,
+            home_x: ai.home.x,
+            home_y: ai.home.y,
+            wander_radius: ai.wander_radius,
+            sight_range: ai.sight_range,
+            flee_percent: ai.flee_percent
+        }
+    }
+
+    fn into_ai(self) -> Ai {
+        Ai {
+            state: self.state,
+            home: TilePos { x: self.home_x, y: self.home_y },
+            wander_radius: self.wander_radius,
+            sight_range: self.sight_range,
+            flee_percent: self.flee_percent
+        }
+    }
+}
+
+impl SavedLifeform {
+    fn from_floor_lifeform(entity: &FloorLifeform) -> SavedLifeform {
+        let lifeform = &entity.lifeform;
+        SavedLifeform {
+            texture: lifeform.texture.texture.0,
+            tile_data: lifeform.texture.tile_data,
+            x: lifeform.position.x,
+            y: lifeform.position.y,
+            health: lifeform.health,
+            max_health: lifeform.max_health,
+            strength: lifeform.strength,
+            defense: lifeform.defense,
+            level: lifeform.level,
+            experience: lifeform.experience,
+            energy: entity.energy.clone(),
+            ai: entity.ai.as_ref().map(SavedAi::from_ai)
+        }
+    }
+
+    fn into_floor_lifeform(self) -> FloorLifeform {
+        let lifeform = Lifeform {
+            texture: TerrainData {
+                texture: TileTextureIndex(self.texture),
+                tile_data: self.tile_data
+            },
+            position: TilePos { x: self.x, y: self.y },
+            health: self.health,
+            max_health: if self.max_health == 0 { self.health } else { self.max_health },
+            strength: self.strength,
+            defense: self.defense,
+            level: self.level,
+            experience: self.experience
+        };
+        FloorLifeform {
+            lifeform,
+            energy: self.energy,
+            ai: self.ai.map(SavedAi::into_ai)
+        }
+    }
+}
+
+impl SavedFloor {
+    fn from_world_state(depth: u32, world_state: &WorldState) -> SavedFloor {
+        SavedFloor {
+            depth,
+            terrain: world_state.terrain.iter().map(|column| column.iter().map(|terrain| SavedTerrain {
+                texture: terrain.texture.0,
+                tile_data: terrain.tile_data
+            }).collect()).collect(),
+            entities: world_state.entities.iter().map(SavedLifeform::from_floor_lifeform).collect(),
+            fog_width: world_state.fog_of_war.width,
+            fog_height: world_state.fog_of_war.height,
+            fog_of_war: world_state.fog_of_war.tiles.clone()
+        }
+    }
+
+    fn into_world_state(self) -> (u32, WorldState) {
+        let world_state = WorldState {
+            terrain: self.terrain.into_iter().map(|column| column.into_iter().map(|terrain| TerrainData {
+                texture: TileTextureIndex(terrain.texture),
+                tile_data: terrain.tile_data
+            }).collect()).collect(),
+            entities: self.entities.into_iter().map(SavedLifeform::into_floor_lifeform).collect(),
+            fog_of_war: FogOfWar {
+                width: self.fog_width,
+                height: self.fog_height,
+                tiles: self.fog_of_war
+            }
+        };
+        (self.depth, world_state)
+    }
+}
+
+pub fn has_saved_run(pkv: &PkvStore) -> bool {
+    matches!(pkv.get::<Option<SavedRun>>(SAVE_KEY), Ok(Some(_)))
+}
+
+pub fn delete_saved_run(pkv: &mut PkvStore) {
+    if let Err(error) = pkv.set(SAVE_KEY, &Option::<SavedRun>::None) {
+        warn!("Couldn't delete the saved run: {:?}", error);
+    }
+}
+
+pub fn save_on_quit(
+    mut ev_close_requested: EventReader<WindowCloseRequested>,
+    mut ev_app_exit: EventReader<AppExit>,
+    mut ev_save_and_quit: EventReader<SaveAndQuitEvent>,
+    mut pkv: ResMut<PkvStore>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+    dungeon: Res<Dungeon>,
+    run_seed: Res<RunSeed>,
+    scheduler: Res<TurnScheduler>,
+    run_stats: Res<RunStats>,
+    fog_of_war: Res<FogOfWar>,
+    lifeforms: Query<(&TilePos, &Lifeform, &Energy, Option<&Ai>)>
+) {
+    if ev_close_requested.is_empty() && ev_app_exit.is_empty() && ev_save_and_quit.is_empty() {
+        return;
+    }
+    ev_close_requested.clear();
+    ev_app_exit.clear();
+    ev_save_and_quit.clear();
+    let Some(seed) = run_seed.current else {
+        return;
+    };
+
+    let current_floor = WorldState {
+        terrain: dungeon.current_terrain.clone(),
+        entities: lifeforms.iter().map(|(tile_pos, lifeform, energy, ai)| {
+            let mut lifeform = lifeform.clone();
+            lifeform.position = *tile_pos;
+            FloorLifeform {
+                lifeform,
+                energy: Some(energy.clone()),
+                ai: ai.cloned()
+            }
+        }).collect(),
+        fog_of_war: fog_of_war.clone()
+    };
+
+    let mut rng_seed = [0u8; 32];
+    rng.fill_bytes(&mut rng_seed);
+    rng.reseed(rng_seed);
+
+    let saved_run = SavedRun {
+        depth: dungeon.depth,
+        arrived_from_below: dungeon.arrived_from_below,
+        current_floor: SavedFloor::from_world_state(dungeon.depth, &current_floor),
+        other_floors: dungeon.floors.iter().map(|(depth, floor)| SavedFloor::from_world_state(*depth, floor)).collect(),
+        ticks: scheduler.ticks,
+        turns: scheduler.turns,
+        kills: run_stats.kills,
+        deepest_floor: run_stats.deepest_floor,
+        seed,
+        rng_seed,
+        profiles: dungeon.profiles.clone()
+    };
+
+    if let Err(error) = pkv.set(SAVE_KEY, &Some(saved_run)) {
+        warn!("Couldn't save the run: {:?}", error);
+    }
+}
+
+pub fn load_run(
+    mut commands: Commands,
+    mut pkv: ResMut<PkvStore>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+    mut run_seed: ResMut<RunSeed>,
+    texture_array: Res<TextureArray>,
+    mut next_state: ResMut<NextState<AppState>>
+) {
+    let Ok(Some(saved_run)) = pkv.get::<Option<SavedRun>>(SAVE_KEY) else {
+        next_state.set(AppState::Generate);
+        return;
+    };
+    // True roguelike, there's no going back to this save once it's been loaded
+    delete_saved_run(&mut pkv);
+
+    rng.reseed(saved_run.rng_seed);
+    run_seed.current = Some(saved_run.seed);
+
+    let mut floors = HashMap::new();
+    for floor in saved_run.other_floors {
+        let (depth, world_state) = floor.into_world_state();
+        floors.insert(depth, world_state);
+    }
+    let (_, current_floor) = saved_run.current_floor.into_world_state();
+
+    commands.insert_resource(Dungeon {
+        depth: saved_run.depth,
+        carried_player: None,
+        floors,
+        current_terrain: current_floor.terrain.clone(),
+        arrived_from_below: saved_run.arrived_from_below,
+        profiles: saved_run.profiles
+    });
+    commands.insert_resource(TurnScheduler {
+        ticks: saved_run.ticks,
+        turns: saved_run.turns
+    });
+    commands.insert_resource(RunStats {
+        deepest_floor: saved_run.deepest_floor,
+        kills: saved_run.kills,
+        outcome: None
+    });
+
+    let map_size = current_floor.map_size();
+    spawn_floor(&mut commands, current_floor, &map_size, &texture_array);
+    next_state.set(AppState::Play);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lifeforms_saved_without_max_health_still_load() {
+        let json = r#"{
+            "texture": 3, "tile_data": {"type": "Enemy"}, "x": 4, "y": 5,
+            "health": 7, "strength": 2, "defense": 1, "level": 1, "experience": 0,
+            "energy": {"current": 0, "speed": 100}, "ai": null
+        }"#;
+        let saved: SavedLifeform = serde_json::from_str(json).unwrap();
+        let floor_lifeform = saved.into_floor_lifeform();
+        assert_eq!(floor_lifeform.lifeform.health, 7);
+        assert_eq!(floor_lifeform.lifeform.max_health, 7);
+    }
+}
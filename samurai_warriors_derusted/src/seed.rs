@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::GlobalEntropy;
+use rand_core::RngCore;
+use crate::utils::{arg_value, parse_arg};
+
+pub const SEED_ARG: &str = "--seed";
+pub const SEED_ENV_VAR: &str = "SAMURAI_SEED";
+
+#[derive(Resource, Default)]
+pub struct RunSeed {
+    // Asked for on the command line or from the options menu, every new run starts from it while it's set
+    pub requested: Option<u64>,
+    // The seed of the run being played, None between runs
+    pub current: Option<u64>
+}
+
+#[derive(Component)]
+pub struct SeedUi;
+
+// Accepts "--seed 1234" or "--seed=1234", falling back on the environment variable
+// A seed that isn't a number is an error rather than quietly becoming a random run
+pub fn parse_seed(args: &[String], env_seed: Option<String>) -> Result<Option<u64>, String> {
+    if args.iter().any(|arg| arg == SEED_ARG || arg.starts_with("--seed=")) {
+        if arg_value(args, SEED_ARG).is_none() {
+            return Err(format!("{} expects a number", SEED_ARG));
+        }
+        return parse_arg(args, SEED_ARG, 0).map(Some);
+    }
+    let Some(env_seed) = env_seed else {
+        return Ok(None);
+    };
+    env_seed.trim().parse().map(Some).map_err(|_| format!("{} expects a number, got {}", SEED_ENV_VAR, env_seed))
+}
+
+pub fn requested_seed() -> Result<Option<u64>, String> {
+    let args: Vec<String> = std::env::args().collect();
+    parse_seed(&args, std::env::var(SEED_ENV_VAR).ok())
+}
+
+pub fn start_run(
+    mut run_seed: ResMut<RunSeed>,
+    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>
+) {
+    if run_seed.current.is_none() {
+        run_seed.current = Some(run_seed.requested.unwrap_or_else(|| rng.next_u64()));
+    }
+}
+
+pub fn spawn_seed_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle {
+            font_size: 18.0,
+            color: Color::WHITE,
+            ..default()
+        }).with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        }),
+        SeedUi
+    ));
+}
+
+pub fn update_seed_text(
+    run_seed: Res<RunSeed>,
+    mut seed_text: Query<&mut Text, With<SeedUi>>
+) {
+    for mut text in &mut seed_text {
+        text.sections[0].value = match run_seed.current {
+            Some(seed) => format!("Seed: {}", seed),
+            None => String::new()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn seed_comes_from_args_before_env() {
+        assert_eq!(parse_seed(&args(&["game", "--seed", "42"]), Some("7".to_string())), Ok(Some(42)));
+        assert_eq!(parse_seed(&args(&["game", "--seed=42"]), None), Ok(Some(42)));
+        assert_eq!(parse_seed(&args(&["game"]), Some(" 7 ".to_string())), Ok(Some(7)));
+        assert_eq!(parse_seed(&args(&["game"]), None), Ok(None));
+    }
+
+    #[test]
+    fn seeds_that_are_not_numbers_are_errors() {
+        assert!(parse_seed(&args(&["game", "--seed", "abc"]), None).is_err());
+        assert!(parse_seed(&args(&["game", "--seed=-1"]), Some("7".to_string())).is_err());
+        assert!(parse_seed(&args(&["game"]), Some("abc".to_string())).is_err());
+    }
+
+    #[test]
+    fn seed_flag_without_a_value_is_an_error() {
+        assert_eq!(parse_seed(&args(&["game", "--seed"]), Some("7".to_string())), Err("--seed expects a number".to_string()));
+        assert_eq!(parse_seed(&args(&["game", "--seed"]), None), Err("--seed expects a number".to_string()));
+    }
+}
@@ -1,96 +1,26 @@
 use std::any::Any;
 use crate::LoadedAssetData;
-use bevy::ecs::system::{Res, ResMut};
-use bevy_ecs_tilemap::prelude::TileTextureIndex;
-use bevy_rand::resource::GlobalEntropy;
-use bevy_prng::ChaCha8Rng;
+use bevy::ecs::system::Res;
+use bevy_ecs_tilemap::prelude::{TilePos, TileTextureIndex, TilemapSize};
+use rand_core::RngCore;
 use crate::Lifeform;
 use bevy::prelude::{Component, Handle, Image, Resource};
 use asset_loading_plugin::loader::{Loadable};
-use crate::worldgen::TerrainData;
-use crate::utils::rand_range;
-
-#[derive(Default, Eq, Hash, PartialEq, Clone, Debug, Copy, serde::Serialize, serde::Deserialize, bevy::asset::Asset, bevy::reflect::TypePath)]
-#[serde(tag = "type")]
-pub enum TileTextureData {
-    #[default]
-    None,
-    Floor,
-    Corridor {
-        start: bool
-    },
-    Player,
-    Enemy,
-    Wall {
-        #[serde(alias="north")]
-        connects_north: bool,
-        #[serde(alias="south")]
-        connects_south: bool,
-        #[serde(alias="east")]
-        connects_east: bool,
-        #[serde(alias="west")]
-        connects_west: bool
-    },
-    Entrance,
-    Exit
-}
-
-impl TileTextureData {
-    pub fn can_replace(&self, old: TileTextureData) -> bool {
-        return match (self, old) {
-            (TileTextureData::Corridor{start},TileTextureData::Corridor{start: old_start}) => {
-                *start || !old_start
-            }
-            (_,TileTextureData::Entrance) => {false}
-            (_,TileTextureData::Exit) => {false}
-            (TileTextureData::Corridor{..},_) => {true}
-            (TileTextureData::Floor, TileTextureData::Corridor{..}) => {false}
-            (TileTextureData::Wall{..}, TileTextureData::Corridor{..}) => {false}
-            (TileTextureData::Wall{..}, TileTextureData::Floor) => {false}
-            (TileTextureData::Enemy, TileTextureData::Player) => {false}
-            (TileTextureData::Player, TileTextureData::Enemy) => {true}
-            (TileTextureData::Enemy, _) => {panic!("Enemy replacing non-entity")}
-            (TileTextureData::Player, _) => {panic!("Player replacing non-entity")}
-            (_,_) => {true}
-        }
-    }
-
-    pub fn makes_walls(&self) -> bool {
-        return match self {
-            TileTextureData::Corridor{..} => {true}
-            TileTextureData::Floor => {true}
-            _ => {false}
-        }
-    }
-
-    pub fn connects_to_walls(&self) -> bool {
-        return match self {
-            TileTextureData::Wall{..} => {true}
-            _ => {false}
-        }
-    }
+use menu_system_plugin::sprite::MenuTexture;
+use crate::ai::Ai;
+use crate::fov::FogOfWar;
+use crate::turn::Energy;
+use worldgen::rand_range;
+pub use worldgen::TileTextureData;
 
-    pub fn repair_tile_data(&self) -> TileTextureData {
-        match self {
-            TileTextureData::Wall {connects_north, connects_south, connects_east, connects_west} => {
-                if !connects_north && !connects_south && !connects_east && !connects_west{
-                    TileTextureData::Wall {
-                        connects_north: false,
-                        connects_east: true,
-                        connects_south: false,
-                        connects_west: true
-                    }
-                } else {
-                    *self
-                }
-            }
-            _ => {
-                *self
-            }
-        }
-    }
+// Rendering and gameplay data the Bevy side hangs off the generator's tiles
+pub trait TileTextureDataExt {
+    fn pick_texture<R: RngCore>(&self, tile_data_holder: &Res<LoadedAssetData>, rng: &mut R) -> TileTextureIndex;
+    fn get_tile_data(&self) -> TileData;
+}
 
-    pub fn pick_texture(&self, tile_data_holder: &Res<LoadedAssetData>, rng: &mut ResMut<GlobalEntropy<ChaCha8Rng>>) -> TileTextureIndex {
+impl TileTextureDataExt for TileTextureData {
+    fn pick_texture<R: RngCore>(&self, tile_data_holder: &Res<LoadedAssetData>, rng: &mut R) -> TileTextureIndex {
         if tile_data_holder.asset_data.contains_key(self) {
 
             let num_textures = tile_data_holder.asset_data[self].len();
@@ -106,29 +36,25 @@ impl TileTextureData {
         panic!("Requested Texture for unknown Tile Data: {:#?}", self);
     }
 
-    fn is_passable(&self) -> bool {
-        return match self {
-            TileTextureData::None => {false}
-            TileTextureData::Floor => {true}
-            TileTextureData::Corridor { .. } => {true}
-            TileTextureData::Player => {false}
-            TileTextureData::Enemy => {false}
-            TileTextureData::Wall { .. } => {false}
-            TileTextureData::Entrance => {true}
-            TileTextureData::Exit => {true}
-        }
-    }
-
-    pub fn get_tile_data(&self) -> TileData {
+    fn get_tile_data(&self) -> TileData {
         return TileData {
-            passable: self.is_passable()
+            passable: self.is_passable(),
+            opaque: self.is_opaque()
         }
     }
 }
 
+// A generated tile along with the texture picked for it
+#[derive(Clone)]
+pub struct TerrainData {
+    pub texture: TileTextureIndex,
+    pub tile_data: TileTextureData
+}
+
 #[derive(Default, Component)]
 pub struct TileData {
-    pub(crate) passable: bool
+    pub(crate) passable: bool,
+    pub(crate) opaque: bool
 }
 
 #[derive(Default, Clone)]
@@ -144,12 +70,83 @@ impl Loadable for RelatedTextureData {
     }
 }
 
+// Lets the menus be drawn with the same tiles as the dungeon
+impl MenuTexture for RelatedTextureData {
+    fn image(&self) -> Handle<Image> {
+        self.texture_handle.clone()
+    }
+}
+
 #[derive(Resource)]
 pub struct TextureArray {
     pub textures: Vec<Handle<Image>>
 }
 
+// A lifeform on a floor along with how far through its turn it was and what it was up to
+// Freshly generated lifeforms have neither yet, they're given new ones when spawned
+#[derive(Clone)]
+pub struct FloorLifeform {
+    pub lifeform: Lifeform,
+    pub energy: Option<Energy>,
+    pub ai: Option<Ai>
+}
+
+impl From<Lifeform> for FloorLifeform {
+    fn from(lifeform: Lifeform) -> FloorLifeform {
+        FloorLifeform {
+            lifeform,
+            energy: None,
+            ai: None
+        }
+    }
+}
+
 pub struct WorldState {
     pub terrain: Vec<Vec<TerrainData>>,
-    pub entities: Vec<Lifeform>
+    pub entities: Vec<FloorLifeform>,
+    pub fog_of_war: FogOfWar
+}
+
+impl WorldState {
+    pub fn map_size(&self) -> TilemapSize {
+        TilemapSize {
+            x: self.terrain.len() as u32,
+            y: self.terrain.first().map_or(0, |column| column.len()) as u32
+        }
+    }
+
+    pub fn find_tile(&self, tile_data: TileTextureData) -> Option<TilePos> {
+        for x in 0..self.terrain.len() {
+            for y in 0..self.terrain[x].len() {
+                if self.terrain[x][y].tile_data == tile_data {
+                    return Some(TilePos { x: x as u32, y: y as u32 });
+                }
+            }
+        }
+        None
+    }
+
+    pub fn lifeform_at(&self, position: &TilePos) -> Option<usize> {
+        self.entities.iter().position(|entity| entity.lifeform.position == *position)
+    }
+
+    // A passable neighboring tile with nobody standing on it
+    pub fn free_neighbor(&self, position: &TilePos) -> Option<TilePos> {
+        let neighbors = [
+            (position.x as i64, position.y as i64 + 1),
+            (position.x as i64, position.y as i64 - 1),
+            (position.x as i64 + 1, position.y as i64),
+            (position.x as i64 - 1, position.y as i64)
+        ];
+        for (x, y) in neighbors {
+            if x < 0 || y < 0 || x as usize >= self.terrain.len() || y as usize >= self.terrain[x as usize].len() {
+                continue;
+            }
+            let neighbor = TilePos { x: x as u32, y: y as u32 };
+            if self.terrain[x as usize][y as usize].tile_data.is_passable() && self.lifeform_at(&neighbor).is_none() {
+                return Some(neighbor);
+            }
+        }
+        None
+    }
 }
\ No newline at end of file
@@ -0,0 +1,231 @@
+use bevy::prelude::{Commands, Component, Entity, Event, EventReader, EventWriter, Query, Res, ResMut, Resource, SystemSet, With};
+use bevy_ecs_tilemap::prelude::TilePos;
+use crate::fov::{FogOfWar, TileVisibility};
+use crate::lifeform::{Enemy, Lifeform, PlayerCharacter};
+
+// Energy a lifeform needs before it may act, and what each action costs
+pub const ACTION_THRESHOLD: u32 = 100;
+pub const DEFAULT_SPEED: u32 = 10;
+// The player heals 1 health every this many turns
+pub const REGEN_INTERVAL: u64 = 3;
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TurnSet {
+    // Enemies that became ready during the last advance decide what to do
+    Act,
+    // The player decides what to do, only while they have enough energy
+    PlayerInput,
+    // Moves, attacks, etc. are applied to the world
+    Resolve,
+    // Energy is spent and time moves forward until the player may act again
+    Advance
+}
+
+#[derive(Resource, Default)]
+pub struct TurnScheduler {
+    pub ticks: u64,
+    pub turns: u64
+}
+
+#[derive(Component, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Energy {
+    pub current: u32,
+    pub speed: u32
+}
+
+impl Energy {
+    pub fn new(speed: u32) -> Energy {
+        Energy {
+            current: 0,
+            speed
+        }
+    }
+
+    pub fn ready(speed: u32) -> Energy {
+        Energy {
+            current: ACTION_THRESHOLD,
+            speed
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.current >= ACTION_THRESHOLD
+    }
+}
+
+// Sent whenever a lifeform spends its turn (moving, attacking, skipping)
+#[derive(Event)]
+pub struct TurnTakenEvent {
+    pub entity: Entity
+}
+
+// Sent when a non-player lifeform has built up enough energy to act
+#[derive(Event)]
+pub struct ActorReadyEvent {
+    pub entity: Entity
+}
+
+// The player is resting, turns go by without any input until they're healed, an enemy comes into view or they're hurt
+#[derive(Resource)]
+pub struct Resting {
+    // What the player's health would be had nothing hurt them since they started resting
+    pub expected_health: u32
+}
+
+impl Resting {
+    pub fn new(health: u32) -> Resting {
+        Resting {
+            expected_health: health
+        }
+    }
+
+    fn should_stop(&self, lifeform: &Lifeform, enemy_in_view: bool) -> bool {
+        enemy_in_view || lifeform.health >= lifeform.max_health || lifeform.health < self.expected_health
+    }
+}
+
+pub fn player_ready(player: Query<&Energy, With<PlayerCharacter>>) -> bool {
+    match player.get_single() {
+        Ok(energy) => energy.is_ready(),
+        Err(_) => false
+    }
+}
+
+pub fn spend_energy(
+    mut ev_turn_taken: EventReader<TurnTakenEvent>,
+    mut scheduler: ResMut<TurnScheduler>,
+    mut player: Query<&mut Energy, With<PlayerCharacter>>
+) {
+    // Other lifeforms pay for their action up front when they're made ready
+    for ev in ev_turn_taken.read() {
+        if let Ok(mut energy) = player.get_mut(ev.entity) {
+            energy.current = energy.current.saturating_sub(ACTION_THRESHOLD);
+            scheduler.turns += 1;
+        }
+    }
+}
+
+// Only game state stops a rest, never a key press, so replays rest exactly as long as the recording did
+pub fn keep_resting(
+    mut commands: Commands,
+    mut resting: ResMut<Resting>,
+    fog_of_war: Res<FogOfWar>,
+    player: Query<(Entity, &Lifeform), With<PlayerCharacter>>,
+    enemies: Query<&TilePos, With<Enemy>>,
+    mut ev_turn_taken: EventWriter<TurnTakenEvent>
+) {
+    let Ok((player_entity, lifeform)) = player.get_single() else {
+        return;
+    };
+    let enemy_in_view = enemies.iter()
+        .any(|tile_pos| fog_of_war.get((tile_pos.x as usize, tile_pos.y as usize)) == TileVisibility::Visible);
+    if resting.should_stop(lifeform, enemy_in_view) {
+        commands.remove_resource::<Resting>();
+        return;
+    }
+    resting.expected_health = lifeform.health;
+    ev_turn_taken.send(TurnTakenEvent { entity: player_entity });
+}
+
+// Has to run after spend_energy has counted the turn
+pub fn regenerate(
+    mut ev_turn_taken: EventReader<TurnTakenEvent>,
+    scheduler: Res<TurnScheduler>,
+    resting: Option<ResMut<Resting>>,
+    mut player: Query<&mut Lifeform, With<PlayerCharacter>>
+) {
+    let mut healed = 0;
+    for ev in ev_turn_taken.read() {
+        let Ok(mut lifeform) = player.get_mut(ev.entity) else {
+            continue;
+        };
+        // The dead don't heal
+        if lifeform.health > 0 && lifeform.health < lifeform.max_health && scheduler.turns % REGEN_INTERVAL == 0 {
+            lifeform.health += 1;
+            healed += 1;
+        }
+    }
+    // Healing doesn't count against a rest, only losing health does
+    if let Some(mut resting) = resting {
+        resting.expected_health += healed;
+    }
+}
+
+pub fn advance_turns(
+    mut scheduler: ResMut<TurnScheduler>,
+    mut actors: Query<(Entity, &mut Energy, Option<&PlayerCharacter>)>,
+    mut ev_actor_ready: EventWriter<ActorReadyEvent>
+) {
+    let mut has_player = false;
+    for (_, energy, player) in &actors {
+        if player.is_some() {
+            if energy.is_ready() {
+                // Nothing happens until the player acts
+                return;
+            }
+            has_player = true;
+        }
+    }
+    if !has_player {
+        return;
+    }
+
+    let mut player_ready = false;
+    while !player_ready {
+        scheduler.ticks += 1;
+        for (entity, mut energy, player) in &mut actors {
+            energy.current += std::cmp::max(energy.speed, 1);
+            if player.is_some() {
+                player_ready = energy.is_ready();
+            } else {
+                while energy.is_ready() {
+                    energy.current -= ACTION_THRESHOLD;
+                    ev_actor_ready.send(ActorReadyEvent { entity });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use worldgen::{LifeformSpawn, TileTextureData};
+
+    fn player(health: u32, max_health: u32) -> Lifeform {
+        let mut lifeform = Lifeform::from(LifeformSpawn {
+            tile_data: TileTextureData::Player,
+            x: 0,
+            y: 0,
+            health,
+            strength: 1,
+            defense: 0,
+            level: 0,
+            experience: 0
+        });
+        lifeform.max_health = max_health;
+        lifeform
+    }
+
+    #[test]
+    fn resting_goes_on_while_hurt_and_alone() {
+        assert!(!Resting::new(10).should_stop(&player(10, 20), false));
+        // Healed since the last turn
+        assert!(!Resting::new(10).should_stop(&player(11, 20), false));
+    }
+
+    #[test]
+    fn resting_stops_at_full_health() {
+        assert!(Resting::new(19).should_stop(&player(20, 20), false));
+    }
+
+    #[test]
+    fn resting_stops_when_an_enemy_is_seen() {
+        assert!(Resting::new(10).should_stop(&player(10, 20), true));
+    }
+
+    #[test]
+    fn resting_stops_when_hurt() {
+        assert!(Resting::new(10).should_stop(&player(9, 20), false));
+    }
+}
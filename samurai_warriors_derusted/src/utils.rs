@@ -1,8 +1,19 @@
-use bevy::prelude::ResMut;
-use bevy_rand::prelude::GlobalEntropy;
-use bevy_prng::ChaCha8Rng;
-use rand_core::RngCore;
+// Accepts "--name value" or "--name=value"
+pub fn arg_value(args: &[String], name: &str) -> Option<String> {
+    for (idx, arg) in args.iter().enumerate() {
+        if arg == name {
+            return args.get(idx + 1).cloned();
+        }
+        if let Some(value) = arg.strip_prefix(name).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
 
-pub fn rand_range(min: u32, range: u32, rng: &mut ResMut<GlobalEntropy<ChaCha8Rng>>) -> u32 {
-    rng.next_u32() % range + min
-}
\ No newline at end of file
+pub fn parse_arg<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> Result<T, String> {
+    match arg_value(args, name) {
+        Some(value) => value.parse().map_err(|_| format!("{} expects a number, got {}", name, value)),
+        None => Ok(default)
+    }
+}
@@ -0,0 +1,288 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::{TilePos, TileStorage, TilemapId, TilemapSize};
+use worldgen::{DungeonProfiles, RoomGenerator};
+use crate::AppState;
+use crate::game_over::{GameOverEvent, RunOutcome, RunStats};
+use crate::ai::Ai;
+use crate::lifeform::{Enemy, Lifeform, PlayerCharacter};
+use bevy::utils::HashMap;
+use crate::fov::FogOfWar;
+use crate::tile_data::{FloorLifeform, TerrainData, TileTextureData, WorldState};
+use crate::turn::Energy;
+use crate::utils::parse_arg;
+
+pub const MAP_WIDTH_ARG: &str = "--map-width";
+pub const MAP_HEIGHT_ARG: &str = "--map-height";
+
+// Taking the Exit of this floor escapes the dungeon and wins the run
+pub const FINAL_DEPTH: u32 = 10;
+
+// Stat increases enemies get for every floor below the first
+const HEALTH_PER_DEPTH: u32 = 6;
+const STRENGTH_PER_DEPTH: u32 = 2;
+const DEFENSE_PER_DEPTH: u32 = 1;
+
+// Tilemap holding the walls, floors, and stairs of the current floor
+#[derive(Component)]
+pub struct TerrainLayer;
+
+// Tilemap holding the player and every enemy on the current floor
+#[derive(Component)]
+pub struct LifeformLayer;
+
+// The Exit tile of a floor, stepping onto it takes the player down a floor, or out of the dungeon from the final floor
+#[derive(Component)]
+pub struct DownStairs;
+
+// The Entrance tile of a floor, stepping onto it takes the player back up a floor
+#[derive(Component)]
+pub struct UpStairs;
+
+// The size every newly generated floor is made at, floors that already exist keep whatever size they were made at
+#[derive(Resource, serde::Serialize, serde::Deserialize, Eq, PartialEq, Clone, Copy, Debug)]
+pub struct MapConfig {
+    pub width: u32,
+    pub height: u32
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        MapConfig {
+            width: 100,
+            height: 40
+        }
+    }
+}
+
+impl MapConfig {
+    pub fn tilemap_size(&self) -> TilemapSize {
+        TilemapSize {
+            x: self.width,
+            y: self.height
+        }
+    }
+
+    pub fn room_generator(&self) -> RoomGenerator {
+        RoomGenerator::new(self.width as usize, self.height as usize)
+    }
+}
+
+// Accepts "--map-width 300 --map-height 200", either left out keeps its default
+pub fn parse_map_config(args: &[String]) -> Result<MapConfig, String> {
+    let default = MapConfig::default();
+    let map_config = MapConfig {
+        width: parse_arg(args, MAP_WIDTH_ARG, default.width)?,
+        height: parse_arg(args, MAP_HEIGHT_ARG, default.height)?
+    };
+    if !map_config.room_generator().fits() {
+        return Err(format!("{}x{} is too small to fit a room", map_config.width, map_config.height));
+    }
+    Ok(map_config)
+}
+
+pub fn map_config() -> Result<MapConfig, String> {
+    let args: Vec<String> = std::env::args().collect();
+    parse_map_config(&args)
+}
+
+#[derive(Resource)]
+pub struct Dungeon {
+    // 1 is the first floor, counting up as the player goes down
+    pub depth: u32,
+    // The player's stats, held on to while the next floor is generated
+    pub carried_player: Option<Lifeform>,
+    // Floors the player has left, kept so they're exactly as they were left when the player returns
+    pub floors: HashMap<u32, WorldState>,
+    // Terrain of the floor currently being played, it never changes once spawned
+    pub current_terrain: Vec<Vec<TerrainData>>,
+    // Whether the player reached the current floor by climbing up from the one below
+    pub arrived_from_below: bool,
+    // The generation profiles the run started with, taken when its first floor is generated
+    pub profiles: Option<DungeonProfiles>
+}
+
+impl Default for Dungeon {
+    fn default() -> Self {
+        Dungeon {
+            depth: 1,
+            carried_player: None,
+            floors: HashMap::new(),
+            current_terrain: vec![],
+            arrived_from_below: false,
+            profiles: None
+        }
+    }
+}
+
+impl Dungeon {
+    // Toughens the enemies of a freshly generated floor to match its depth
+    pub fn scale_enemies(&self, world_state: &mut WorldState) {
+        let extra_depth = self.depth - 1;
+        for FloorLifeform { lifeform, .. } in &mut world_state.entities {
+            if lifeform.texture.tile_data != TileTextureData::Player {
+                lifeform.health += HEALTH_PER_DEPTH * extra_depth;
+                lifeform.max_health += HEALTH_PER_DEPTH * extra_depth;
+                lifeform.strength += STRENGTH_PER_DEPTH * extra_depth;
+                lifeform.defense += DEFENSE_PER_DEPTH * extra_depth;
+                lifeform.level += extra_depth;
+            }
+        }
+    }
+
+    // Puts the carried player on the stairs they arrived by, replacing whoever the generator placed
+    pub fn place_player(&mut self, world_state: &mut WorldState) {
+        let Some(mut player) = self.carried_player.take() else {
+            // First floor of a run, the generated player is the real one
+            return;
+        };
+
+        let stairs = if self.arrived_from_below { TileTextureData::Exit } else { TileTextureData::Entrance };
+        let generated_position = world_state.entities.iter()
+            .find(|entity| entity.lifeform.texture.tile_data == TileTextureData::Player)
+            .map(|entity| entity.lifeform.position);
+        let Some(position) = world_state.find_tile(stairs).or(generated_position) else {
+            return;
+        };
+        world_state.entities.retain(|entity| entity.lifeform.texture.tile_data != TileTextureData::Player);
+
+        // Anything loitering on the stairs since the player left gets nudged out of the way
+        if let Some(idx) = world_state.lifeform_at(&position) {
+            match world_state.free_neighbor(&position) {
+                Some(free_position) => world_state.entities[idx].lifeform.position = free_position,
+                None => { world_state.entities.remove(idx); }
+            }
+        }
+
+        player.position = position;
+        world_state.entities.push(player.into());
+    }
+}
+
+// Where a flight of stairs leads from a floor
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum StairsDestination {
+    Floor(u32),
+    // Down from the final floor, out of the dungeon
+    Escape
+}
+
+// The first floor's Entrance leads nowhere
+pub fn stairs_destination(depth: u32, down: bool) -> Option<StairsDestination> {
+    match (down, depth) {
+        (true, depth) if depth >= FINAL_DEPTH => Some(StairsDestination::Escape),
+        (true, depth) => Some(StairsDestination::Floor(depth + 1)),
+        (false, depth) if depth > 1 => Some(StairsDestination::Floor(depth - 1)),
+        (false, _) => None
+    }
+}
+
+// Descend or Ascend was pressed, it only does anything while the player stands on the matching stairs
+#[derive(Event)]
+pub struct UseStairsEvent {
+    pub down: bool
+}
+
+// Every tile and tilemap entity that makes up the current floor
+pub type FloorEntities<'w, 's> = Query<'w, 's, Entity, Or<(With<TilemapId>, With<TileStorage>)>>;
+
+// Removes both tilemaps and every tile on them, ready for the next floor to be spawned
+pub fn despawn_floor(commands: &mut Commands, floor: &FloorEntities) {
+    for entity in floor {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn take_stairs(
+    mut commands: Commands,
+    mut dungeon: ResMut<Dungeon>,
+    mut run_stats: ResMut<RunStats>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut ev_use_stairs: EventReader<UseStairsEvent>,
+    mut ev_game_over: EventWriter<GameOverEvent>,
+    player: Query<(Ref<TilePos>, &Lifeform), With<PlayerCharacter>>,
+    enemies: Query<(&TilePos, &Lifeform, &Energy, &Ai), With<Enemy>>,
+    terrain_layer: Query<&TileStorage, (With<TerrainLayer>, Without<LifeformLayer>)>,
+    down_stairs: Query<(), With<DownStairs>>,
+    up_stairs: Query<(), With<UpStairs>>,
+    fog_of_war: Res<FogOfWar>,
+    floor: FloorEntities
+) {
+    let (Ok((player_pos, lifeform)), Ok(terrain_storage)) = (player.get_single(), terrain_layer.get_single()) else {
+        return;
+    };
+    let requested_down = ev_use_stairs.read().last().map(|ev| ev.down);
+    // Walking onto the stairs takes them, arriving on them from another floor needs Descend or Ascend pressing
+    let walked_on = player_pos.is_changed() && !player_pos.is_added();
+    if !walked_on && requested_down.is_none() {
+        return;
+    }
+    let Some(tile_entity) = terrain_storage.checked_get(&player_pos) else {
+        return;
+    };
+
+    let down = if down_stairs.contains(tile_entity) && requested_down != Some(false) {
+        true
+    } else if up_stairs.contains(tile_entity) && requested_down != Some(true) {
+        false
+    } else {
+        return;
+    };
+    let next_depth = match stairs_destination(dungeon.depth, down) {
+        Some(StairsDestination::Floor(next_depth)) => next_depth,
+        Some(StairsDestination::Escape) => {
+            ev_game_over.send(GameOverEvent { outcome: RunOutcome::Won });
+            return;
+        }
+        None => return
+    };
+
+    let left_floor = WorldState {
+        terrain: std::mem::take(&mut dungeon.current_terrain),
+        entities: enemies.iter().map(|(tile_pos, enemy, energy, ai)| {
+            let mut enemy = enemy.clone();
+            enemy.position = *tile_pos;
+            FloorLifeform {
+                lifeform: enemy,
+                energy: Some(energy.clone()),
+                ai: Some(ai.clone())
+            }
+        }).collect(),
+        fog_of_war: fog_of_war.clone()
+    };
+    let depth = dungeon.depth;
+    dungeon.floors.insert(depth, left_floor);
+    dungeon.carried_player = Some(lifeform.clone());
+    dungeon.arrived_from_below = next_depth < depth;
+    dungeon.depth = next_depth;
+
+    run_stats.deepest_floor = std::cmp::max(run_stats.deepest_floor, dungeon.depth);
+    despawn_floor(&mut commands, &floor);
+    next_state.set(AppState::Generate);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn map_size_comes_from_args() {
+        assert_eq!(parse_map_config(&args(&["game"])), Ok(MapConfig::default()));
+        assert_eq!(parse_map_config(&args(&["game", "--map-width", "300", "--map-height=200"])), Ok(MapConfig { width: 300, height: 200 }));
+        assert_eq!(parse_map_config(&args(&["game", "--map-width", "400"])), Ok(MapConfig { width: 400, height: 40 }));
+        assert!(parse_map_config(&args(&["game", "--map-width", "wide"])).is_err());
+        assert!(parse_map_config(&args(&["game", "--map-height", "5"])).is_err());
+    }
+
+    #[test]
+    fn exit_of_the_final_floor_wins_the_run() {
+        assert_eq!(stairs_destination(1, true), Some(StairsDestination::Floor(2)));
+        assert_eq!(stairs_destination(1, false), None);
+        assert_eq!(stairs_destination(FINAL_DEPTH, false), Some(StairsDestination::Floor(FINAL_DEPTH - 1)));
+        assert_eq!(stairs_destination(FINAL_DEPTH - 1, true), Some(StairsDestination::Floor(FINAL_DEPTH)));
+        assert_eq!(stairs_destination(FINAL_DEPTH, true), Some(StairsDestination::Escape));
+    }
+}
@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+use bevy::asset::io::file::FileAssetReader;
+use bevy_prng::ChaCha8Rng;
+use rand_core::SeedableRng;
+use worldgen::{DungeonProfiles, GenerationReport, GeneratorConfig, GeneratorKind, LifeformSpawn, TileTextureData, floor_seed};
+use crate::profiles::DUNGEON_PROFILES_PATH;
+use crate::seed::{SEED_ENV_VAR, parse_seed};
+use crate::utils::{arg_value, parse_arg};
+use crate::world::parse_map_config;
+
+// `samurai_warriors_derusted worldgen [--seed N] [--depth N] [--generator rooms|bsp|caves|tunnels] [--profiles PATH] [--map-width N] [--map-height N] [--count N]`
+// The seed and map size are given the same way as they are to the game, and the profiles come from the game's own file unless --profiles says otherwise
+pub const WORLDGEN_COMMAND: &str = "worldgen";
+
+struct WorldgenOptions {
+    seed: u64,
+    depth: u32,
+    generator: GeneratorConfig,
+    width: usize,
+    height: usize,
+    count: u32
+}
+
+fn parse_options(args: &[String]) -> Result<WorldgenOptions, String> {
+    // Without a seed every batch is different, but it's always printed so an interesting map can be found again
+    let seed = parse_seed(args, std::env::var(SEED_ENV_VAR).ok())?.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64)
+    });
+
+    let map_config = parse_map_config(args)?;
+    let depth = parse_arg(args, "--depth", 1)?;
+    // Left out, it's whichever generator and settings the game would use at this depth
+    let generator = match arg_value(args, "--generator") {
+        Some(name) => name.parse::<GeneratorKind>()?.into(),
+        None => {
+            let path = arg_value(args, "--profiles").map_or_else(default_profiles_path, PathBuf::from);
+            load_profiles(&path)?.generator_for(depth)
+        }
+    };
+    Ok(WorldgenOptions {
+        seed,
+        depth,
+        generator,
+        width: map_config.width as usize,
+        height: map_config.height as usize,
+        count: parse_arg(args, "--count", 1)?
+    })
+}
+
+// Where the game loads its profiles from, assets are looked for the same way Bevy does
+fn default_profiles_path() -> PathBuf {
+    FileAssetReader::get_base_path().join("assets").join(DUNGEON_PROFILES_PATH)
+}
+
+fn load_profiles(path: &Path) -> Result<DungeonProfiles, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|error| format!("Couldn't read the dungeon profiles from {}: {}", path.display(), error))?;
+    serde_json::from_str(&contents)
+        .map_err(|error| format!("Couldn't parse the dungeon profiles in {}: {}", path.display(), error))
+}
+
+fn terrain_char(tile_data: &TileTextureData) -> char {
+    match tile_data {
+        TileTextureData::None => ' ',
+        TileTextureData::Floor | TileTextureData::Corridor { .. } => '.',
+        TileTextureData::Wall { .. } => '#',
+        TileTextureData::Entrance => '<',
+        TileTextureData::Exit => '>',
+        TileTextureData::Player => '@',
+        TileTextureData::Enemy => 'e'
+    }
+}
+
+// Rows are printed top to bottom, the tilemap's y grows upwards so the highest row comes first
+pub fn render_ascii(terrain: &[Vec<TileTextureData>], spawns: &[LifeformSpawn]) -> String {
+    let width = terrain.len();
+    let height = terrain.first().map_or(0, |column| column.len());
+    let mut grid: Vec<Vec<char>> = terrain.iter()
+        .map(|column| column.iter().map(terrain_char).collect())
+        .collect();
+    for spawn in spawns {
+        if spawn.x < width && spawn.y < height {
+            grid[spawn.x][spawn.y] = terrain_char(&spawn.tile_data);
+        }
+    }
+
+    let mut output = String::with_capacity((width + 1) * height);
+    for y in (0..height).rev() {
+        for column in &grid {
+            output.push(column[y]);
+        }
+        output.push('\n');
+    }
+    output
+}
+
+fn describe_report(report: &GenerationReport) -> String {
+    let mut description = format!("Corridors added: {} Regenerations: {}", report.corridors_added, report.regenerations);
+    if !report.is_valid() {
+        description.push_str(&format!(" INVALID: exit reachable: {} unreachable regions: {} blocked spawns: {}",
+            report.exit_reachable, report.unreachable_regions.len(), report.blocked_spawns.len()));
+    }
+    description
+}
+
+// Generates exactly what the game would for this seed, depth and generator, before textures are picked
+pub fn generate_ascii(seed: u64, depth: u32, generator: &GeneratorConfig, width: usize, height: usize) -> (String, GenerationReport) {
+    let mut rng = ChaCha8Rng::from_seed(floor_seed(seed, depth));
+    let (terrain, spawns, report) = generator.generate_floor(width, height, &mut rng);
+    (render_ascii(&terrain, &spawns), report)
+}
+
+pub fn run(args: &[String]) {
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+
+    for idx in 0..options.count {
+        let seed = options.seed.wrapping_add(idx as u64);
+        println!("Seed: {} Depth: {} Generator: {} Size: {}x{}", seed, options.depth, options.generator, options.width, options.height);
+        let (map, report) = generate_ascii(seed, options.depth, &options.generator, options.width, options.height);
+        println!("{}", describe_report(&report));
+        println!("{}", map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::MapConfig;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn options_match_the_game_args() {
+        let options = parse_options(&args(&["--seed", "7", "--map-width", "120", "--map-height=50"])).unwrap();
+        assert_eq!((options.seed, options.width, options.height), (7, 120, 50));
+        assert!(parse_options(&args(&["--seed", "seven"])).is_err());
+        assert!(parse_options(&args(&["--seed", "7", "--map-height", "5"])).is_err());
+    }
+
+    #[test]
+    fn generator_comes_from_the_game_profiles() {
+        let profiles = load_profiles(&default_profiles_path()).unwrap();
+        for depth in 1..=6 {
+            let options = parse_options(&args(&["--seed", "7", "--depth", &depth.to_string()])).unwrap();
+            assert_eq!(options.generator.kind(), profiles.generator_for(depth).kind());
+        }
+        let options = parse_options(&args(&["--seed", "7", "--generator", "caves"])).unwrap();
+        assert_eq!(options.generator.kind(), GeneratorKind::Caves);
+        assert!(parse_options(&args(&["--seed", "7", "--profiles", "missing.dungeon.json"])).is_err());
+    }
+
+    #[test]
+    fn map_is_printed_at_full_size() {
+        let map_config = MapConfig::default();
+        for generator in GeneratorKind::ALL {
+            let (map, report) = generate_ascii(7, 1, &generator.into(), map_config.width as usize, map_config.height as usize);
+            assert!(report.is_valid());
+            let lines: Vec<&str> = map.lines().collect();
+            assert_eq!(lines.len(), map_config.height as usize);
+            assert!(lines.iter().all(|line| line.chars().count() == map_config.width as usize));
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use rand_core::RngCore;
+use crate::corridor::{blit, dig_corridor};
+use crate::generator::{MapGenerator, spawn_at};
+use crate::rand_range;
+use crate::rooms::{LifeformSpawn, Rect, Room};
+use crate::tile_data::TileTextureData;
+
+// A piece of the map that hasn't been split any further yet
+#[derive(Clone, Copy)]
+struct Area {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32
+}
+
+// Splits the map in two over and over, one room goes in each piece and every pair of pieces gets a corridor
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct BspGenerator {
+    #[serde(skip)]
+    pub map_width: usize,
+    #[serde(skip)]
+    pub map_height: usize,
+    // Pieces stop being split once either half would be smaller than this
+    pub min_area_size: u32,
+    // Walls included, so the smallest room has a 3x3 floor
+    pub min_room_size: u32,
+    pub max_enemies_per_room: u32
+}
+
+impl BspGenerator {
+    pub fn new(map_width: usize, map_height: usize) -> BspGenerator {
+        BspGenerator {
+            map_width,
+            map_height,
+            min_area_size: 10,
+            min_room_size: 5,
+            max_enemies_per_room: 2
+        }
+    }
+
+    fn place_room<R: RngCore>(&self, area: Area, rng: &mut R, map: &mut [Vec<TileTextureData>]) -> Option<Room> {
+        let min_size = self.min_room_size.max(3);
+        if area.width < min_size || area.height < min_size {
+            return None;
+        }
+        let width = rand_range(min_size, area.width - min_size + 1, rng);
+        let height = rand_range(min_size, area.height - min_size + 1, rng);
+        let x = rand_range(area.x, area.width - width + 1, rng);
+        let y = rand_range(area.y, area.height - height + 1, rng);
+
+        let room = Room::Basic { rect: Rect::new(x, y, width, height) };
+        blit(map, room.clone().render(), x as usize, y as usize);
+        Some(room)
+    }
+
+    // Returns every room placed inside the area, already joined up to each other
+    fn partition<R: RngCore>(&self, area: Area, rng: &mut R, map: &mut [Vec<TileTextureData>]) -> Vec<Room> {
+        let min_size = self.min_area_size.max(self.min_room_size).max(3);
+        let can_split_x = area.width >= min_size * 2;
+        let can_split_y = area.height >= min_size * 2;
+        // Long thin pieces get cut across the long side, so the rooms stay roughly square
+        let split_x = match (can_split_x, can_split_y) {
+            (false, false) => return self.place_room(area, rng, map).into_iter().collect(),
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) => match area.width.cmp(&area.height) {
+                std::cmp::Ordering::Greater => true,
+                std::cmp::Ordering::Less => false,
+                std::cmp::Ordering::Equal => rand_range(0, 2, rng) == 0
+            }
+        };
+
+        let (first, second) = if split_x {
+            let split = rand_range(min_size, area.width - min_size * 2 + 1, rng);
+            (Area { width: split, ..area }, Area { x: area.x + split, width: area.width - split, ..area })
+        } else {
+            let split = rand_range(min_size, area.height - min_size * 2 + 1, rng);
+            (Area { height: split, ..area }, Area { y: area.y + split, height: area.height - split, ..area })
+        };
+
+        let mut first_rooms = self.partition(first, rng, map);
+        let second_rooms = self.partition(second, rng, map);
+        if !first_rooms.is_empty() && !second_rooms.is_empty() {
+            let from_room = &first_rooms[rand_range(0, first_rooms.len() as u32, rng) as usize];
+            let to_room = &second_rooms[rand_range(0, second_rooms.len() as u32, rng) as usize];
+            let from = from_room.get_random_point_in(rng);
+            let to = to_room.get_random_point_in(rng);
+            dig_corridor(map, from, to);
+        }
+        first_rooms.extend(second_rooms);
+        first_rooms
+    }
+}
+
+impl Default for BspGenerator {
+    fn default() -> Self {
+        BspGenerator::new(0, 0)
+    }
+}
+
+impl MapGenerator for BspGenerator {
+    fn generate<R: RngCore>(&mut self, rng: &mut R) -> (Vec<Vec<TileTextureData>>, Vec<LifeformSpawn>) {
+        let mut output = vec![vec![TileTextureData::None; self.map_height]; self.map_width];
+        let mut output_entities = vec![];
+        let area = Area {
+            x: 0,
+            y: 0,
+            width: u32::try_from(self.map_width).unwrap_or(u32::MAX),
+            height: u32::try_from(self.map_height).unwrap_or(u32::MAX)
+        };
+        let rooms = self.partition(area, rng, &mut output);
+        let (Some(first_room), Some(last_room)) = (rooms.first(), rooms.last()) else {
+            return (output, output_entities);
+        };
+
+        for room in &rooms {
+            for _ in 0..rand_range(0, self.max_enemies_per_room, rng) {
+                let (x, y) = room.get_random_point_in(rng);
+                spawn_at(rng, x, y, TileTextureData::Enemy, &mut output_entities);
+            }
+        }
+
+        let (entrance_x, entrance_y) = first_room.get_random_point_in(rng);
+        blit(&mut output, vec![vec![TileTextureData::Entrance]], entrance_x, entrance_y);
+        spawn_at(rng, entrance_x, entrance_y, TileTextureData::Player, &mut output_entities);
+        let (exit_x, exit_y) = last_room.get_random_point_in(rng);
+        blit(&mut output, vec![vec![TileTextureData::Exit]], exit_x, exit_y);
+
+        (output, output_entities)
+    }
+}
@@ -0,0 +1,115 @@
+use rand_core::RngCore;
+use crate::generator::{MapGenerator, populate};
+use crate::rand_range;
+use crate::rooms::LifeformSpawn;
+use crate::tile_data::TileTextureData;
+use crate::validate::flood_fill;
+use crate::walls::EMPTY_WALL;
+
+// Scatters rock at random then smooths it over a few times, like a cellular automaton, into open caverns
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CaveGenerator {
+    #[serde(skip)]
+    pub map_width: usize,
+    #[serde(skip)]
+    pub map_height: usize,
+    // Chance out of 100 that a tile starts out as rock
+    pub fill_percent: u32,
+    pub smoothing_passes: u32,
+    pub floor_per_enemy: u32
+}
+
+impl CaveGenerator {
+    pub fn new(map_width: usize, map_height: usize) -> CaveGenerator {
+        CaveGenerator {
+            map_width,
+            map_height,
+            fill_percent: 45,
+            smoothing_passes: 5,
+            floor_per_enemy: 60
+        }
+    }
+
+    // Off the map counts as rock, so the caves never open onto the edge
+    fn rock_around(rock: &[Vec<bool>], x: usize, y: usize) -> u32 {
+        let mut count = 0;
+        for neighbor_x in x as i64 - 1..=x as i64 + 1 {
+            for neighbor_y in y as i64 - 1..=y as i64 + 1 {
+                if (neighbor_x, neighbor_y) == (x as i64, y as i64) {
+                    continue;
+                }
+                let is_rock = neighbor_x < 0 || neighbor_y < 0
+                    || rock.get(neighbor_x as usize).and_then(|column| column.get(neighbor_y as usize)).is_none_or(|is_rock| *is_rock);
+                if is_rock {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    // Tiles mostly surrounded by rock fill in, tiles mostly surrounded by open space open up
+    fn smooth(rock: &[Vec<bool>]) -> Vec<Vec<bool>> {
+        let width = rock.len();
+        rock.iter().enumerate().map(|(x, column)| {
+            let height = column.len();
+            column.iter().enumerate().map(|(y, is_rock)| {
+                if x == 0 || y == 0 || x + 1 == width || y + 1 == height {
+                    return true;
+                }
+                match Self::rock_around(rock, x, y) {
+                    count if count > 4 => true,
+                    count if count < 4 => false,
+                    _ => *is_rock
+                }
+            }).collect()
+        }).collect()
+    }
+
+    // Pockets cut off from the biggest cavern are filled back in, rather than tunnelled into
+    fn keep_largest_cave(map: &mut [Vec<TileTextureData>]) {
+        let mut visited: Vec<Vec<bool>> = map.iter().map(|column| vec![false; column.len()]).collect();
+        let mut caves = vec![];
+        for x in 0..map.len() {
+            for y in 0..map[x].len() {
+                if !visited[x][y] && map[x][y].is_passable() {
+                    caves.push(flood_fill(map, (x, y), &mut visited));
+                }
+            }
+        }
+        let largest = caves.iter().enumerate().max_by_key(|(_, cave)| cave.len()).map(|(idx, _)| idx);
+        for (idx, cave) in caves.iter().enumerate() {
+            if Some(idx) != largest {
+                for &(x, y) in cave {
+                    map[x][y] = EMPTY_WALL;
+                }
+            }
+        }
+    }
+}
+
+impl Default for CaveGenerator {
+    fn default() -> Self {
+        CaveGenerator::new(0, 0)
+    }
+}
+
+impl MapGenerator for CaveGenerator {
+    fn generate<R: RngCore>(&mut self, rng: &mut R) -> (Vec<Vec<TileTextureData>>, Vec<LifeformSpawn>) {
+        let (width, height) = (self.map_width, self.map_height);
+        let mut rock: Vec<Vec<bool>> = (0..width).map(|x| (0..height).map(|y| {
+            x == 0 || y == 0 || x + 1 == width || y + 1 == height || rand_range(0, 100, rng) < self.fill_percent
+        }).collect()).collect();
+        for _ in 0..self.smoothing_passes {
+            rock = Self::smooth(&rock);
+        }
+
+        let mut output: Vec<Vec<TileTextureData>> = rock.iter()
+            .map(|column| column.iter().map(|is_rock| if *is_rock { EMPTY_WALL } else { TileTextureData::Floor }).collect())
+            .collect();
+        Self::keep_largest_cave(&mut output);
+        let output_entities = populate(&mut output, self.floor_per_enemy, rng);
+        (output, output_entities)
+    }
+}
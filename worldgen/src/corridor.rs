@@ -0,0 +1,117 @@
+use crate::tile_data::TileTextureData;
+use crate::walls::EMPTY_WALL;
+
+pub(crate) struct Corridor {
+    pub from_x: usize,
+    pub to_x: usize,
+    pub from_y: usize,
+    pub to_y: usize
+}
+
+impl Corridor {
+
+    // Anything that would land off the edge of the map is dropped
+    fn set_at(x: usize, y: usize, new_data: TileTextureData, output: &mut [Vec<TileTextureData>]) {
+        if let Some(tile) = output.get_mut(x).and_then(|column| column.get_mut(y)) {
+            if new_data.can_replace(*tile) {
+                *tile = new_data;
+            }
+        }
+    }
+
+    fn is_extents(val: usize, min: usize, max: usize) -> bool {
+        val == min || val == max - 1
+    }
+
+    fn render_corridor_tile(x: usize, y: usize, horizontal: bool, extents: bool, _start: bool, output: &mut [Vec<TileTextureData>]) {
+        if extents {
+            Self::set_at(x, y, EMPTY_WALL, output);
+        } else {
+            Self::set_at(x, y, TileTextureData::Floor, output);
+        }
+        if horizontal {
+            if let Some(below) = y.checked_sub(1) {
+                Self::set_at(x, below, EMPTY_WALL, output);
+            }
+            Self::set_at(x, y + 1, EMPTY_WALL, output);
+        } else {
+            if let Some(left) = x.checked_sub(1) {
+                Self::set_at(left, y, EMPTY_WALL, output);
+            }
+            Self::set_at(x + 1, y, EMPTY_WALL, output);
+        }
+    }
+
+    fn x_corridor(from_x: usize, to_x: usize, at_y: usize, output: &mut [Vec<TileTextureData>]) {
+        let min = std::cmp::min(from_x, to_x).saturating_sub(1);
+        let max = std::cmp::max(from_x, to_x) + 2;
+
+        for x in min..max {
+            Self::render_corridor_tile(x, at_y, true, Self::is_extents(x, min, max), Self::is_extents(x, min + 1, max - 1), output);
+        }
+    }
+
+    fn y_corridor(from_y: usize, to_y: usize, at_x: usize, output: &mut [Vec<TileTextureData>]) {
+        let min = std::cmp::min(from_y, to_y).saturating_sub(1);
+        let max = std::cmp::max(from_y, to_y) + 2;
+
+        for y in min..max {
+            Self::render_corridor_tile(at_x, y, false, Self::is_extents(y, min, max), Self::is_extents(y, min + 1, max - 1), output);
+        }
+    }
+
+    pub fn render(&self) -> Vec<Vec<TileTextureData>> {
+        let (offset_x, offset_y) = self.get_offsets();
+        let (extents_x, extents_y) = self.get_extents();
+        let mut output = vec![vec![TileTextureData::None; extents_y]; extents_x];
+
+        if self.from_x == self.to_x {
+            Self::y_corridor(self.from_y - offset_y, self.to_y - offset_y, self.to_x - offset_x, &mut output);
+        } else if self.from_y == self.to_y {
+            Self::x_corridor(self.from_x - offset_x, self.to_x - offset_x, self.from_y - offset_y, &mut output);
+        } else {
+            Self::y_corridor(self.from_y - offset_y, self.to_y - offset_y, self.to_x - offset_x, &mut output);
+            Self::x_corridor(self.from_x - offset_x, self.to_x - offset_x, self.from_y - offset_y, &mut output);
+        }
+
+        output
+    }
+
+    pub fn get_offsets(&self) -> (usize, usize) {
+        (std::cmp::min(self.from_x, self.to_x).saturating_sub(1), std::cmp::min(self.from_y, self.to_y).saturating_sub(1))
+    }
+
+    fn get_extents(&self) -> (usize, usize) {
+        let (offset_x, offset_y) = self.get_offsets();
+        (std::cmp::max(self.from_x, self.to_x) + 2 - offset_x, std::cmp::max(self.from_y, self.to_y) + 2 - offset_y)
+    }
+}
+
+pub(crate) fn blit(output: &mut [Vec<TileTextureData>], input: Vec<Vec<TileTextureData>>, x: usize, y: usize) {
+    for (input_x, column) in input.iter().enumerate() {
+        for (input_y, input_tile) in column.iter().enumerate() {
+            if *input_tile != TileTextureData::None {
+                // Corridors along the edge of the map can hang over it
+                let Some(tile) = output.get_mut(input_x + x).and_then(|column| column.get_mut(input_y + y)) else {
+                    continue;
+                };
+                if input_tile.can_replace(*tile) {
+                    *tile = *input_tile;
+                }
+            }
+        }
+    }
+}
+
+// An L shaped corridor between two points, walled along both sides
+pub(crate) fn dig_corridor(map: &mut [Vec<TileTextureData>], (from_x, from_y): (usize, usize), (to_x, to_y): (usize, usize)) {
+    let corridor = Corridor {
+        from_x,
+        from_y,
+        to_x,
+        to_y
+    };
+
+    let (x_offset, y_offset) = corridor.get_offsets();
+    blit(map, corridor.render(), x_offset, y_offset);
+}
@@ -0,0 +1,222 @@
+use std::fmt;
+use std::str::FromStr;
+use rand_core::RngCore;
+use crate::corridor::dig_corridor;
+use crate::profile::GeneratorConfig;
+use crate::rand_range;
+use crate::rooms::LifeformSpawn;
+use crate::tile_data::TileTextureData;
+use crate::validate::{GenerationReport, find_tile, flood_fill, validate};
+use crate::walls::connect_walls;
+
+// Past this the last attempt is handed back as is, with a report saying what's still wrong with it
+const MAX_REGENERATIONS: u32 = 10;
+
+pub trait MapGenerator {
+    // Lays out a floor and who starts where on it, walls are joined up afterwards by the shared wall pass
+    fn generate<R: RngCore>(&mut self, rng: &mut R) -> (Vec<Vec<TileTextureData>>, Vec<LifeformSpawn>);
+}
+
+#[derive(Default, Eq, PartialEq, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratorKind {
+    #[default]
+    Rooms,
+    Bsp,
+    Caves,
+    Tunnels
+}
+
+impl GeneratorKind {
+    pub const ALL: [GeneratorKind; 4] = [GeneratorKind::Rooms, GeneratorKind::Bsp, GeneratorKind::Caves, GeneratorKind::Tunnels];
+
+    // The first floor is always rooms, below that every floor takes the next generator in turn
+    pub fn for_depth(depth: u32) -> GeneratorKind {
+        Self::ALL[(depth.saturating_sub(1) as usize) % Self::ALL.len()]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GeneratorKind::Rooms => "rooms",
+            GeneratorKind::Bsp => "bsp",
+            GeneratorKind::Caves => "caves",
+            GeneratorKind::Tunnels => "tunnels"
+        }
+    }
+
+    // The generator with its default settings, for a map this size
+    pub fn generate_floor<R: RngCore>(self, map_width: usize, map_height: usize, rng: &mut R) -> (Vec<Vec<TileTextureData>>, Vec<LifeformSpawn>, GenerationReport) {
+        GeneratorConfig::from(self).generate_floor(map_width, map_height, rng)
+    }
+}
+
+impl fmt::Display for GeneratorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for GeneratorKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| format!("Unknown generator {}, expected one of rooms, bsp, caves or tunnels", name))
+    }
+}
+
+pub(crate) fn spawn_at<R: RngCore>(rng: &mut R, x: usize, y: usize, entity_type: TileTextureData, output: &mut Vec<LifeformSpawn>) {
+    output.push(LifeformSpawn{
+        tile_data: entity_type,
+        x,
+        y,
+        health: rand_range(48, 13, rng),
+        strength: rand_range(5, 11, rng),
+        defense: rand_range(0, 6, rng),
+        level: 0,
+        experience: 1
+    });
+}
+
+// For generators without rooms to put things in: the entrance and player go on a random floor tile, the exit as far
+// from them as the floor allows, and an enemy for every floor_per_enemy tiles of floor anywhere else
+pub(crate) fn populate<R: RngCore>(map: &mut [Vec<TileTextureData>], floor_per_enemy: u32, rng: &mut R) -> Vec<LifeformSpawn> {
+    let mut spawns = vec![];
+    let floor: Vec<(usize, usize)> = map.iter().enumerate()
+        .flat_map(|(x, column)| column.iter().enumerate()
+            .filter(|(_, tile_data)| **tile_data == TileTextureData::Floor)
+            .map(move |(y, _)| (x, y)))
+        .collect();
+    if floor.len() < 2 {
+        return spawns;
+    }
+
+    let (entrance_x, entrance_y) = floor[rand_range(0, floor.len() as u32, rng) as usize];
+    let mut visited: Vec<Vec<bool>> = map.iter().map(|column| vec![false; column.len()]).collect();
+    // Flood filling goes breadth first, so the last tile it reaches is the furthest walk away
+    let Some(&(exit_x, exit_y)) = flood_fill(map, (entrance_x, entrance_y), &mut visited).last() else {
+        return spawns;
+    };
+    map[entrance_x][entrance_y] = TileTextureData::Entrance;
+    map[exit_x][exit_y] = TileTextureData::Exit;
+    spawn_at(rng, entrance_x, entrance_y, TileTextureData::Player, &mut spawns);
+
+    let enemy_count = floor.len() as u32 / floor_per_enemy.max(1);
+    for _ in 0..enemy_count {
+        let (x, y) = floor[rand_range(0, floor.len() as u32, rng) as usize];
+        if map[x][y] == TileTextureData::Floor {
+            spawn_at(rng, x, y, TileTextureData::Enemy, &mut spawns);
+        }
+    }
+    spawns
+}
+
+// Digs a corridor from the region to the closest tile the entrance can already reach
+fn connect_region(region: &[(usize, usize)], map: &mut [Vec<TileTextureData>]) -> bool {
+    let width = map.len();
+    let height = map.first().map_or(0, |column| column.len());
+    // Corridors put walls either side of them, so neither end can sit on the map's edge
+    let in_bounds = |&(x, y): &(usize, usize)| x > 0 && y > 0 && x + 2 < width && y + 2 < height;
+
+    let Some(entrance) = find_tile(map, TileTextureData::Entrance) else {
+        return false;
+    };
+    let mut visited: Vec<Vec<bool>> = map.iter().map(|column| vec![false; column.len()]).collect();
+    let reachable = flood_fill(map, entrance, &mut visited);
+
+    let closest = region.iter().filter(|tile| in_bounds(tile))
+        .flat_map(|from| reachable.iter().filter(|tile| in_bounds(tile)).map(move |to| (*from, *to)))
+        .min_by_key(|((from_x, from_y), (to_x, to_y))| from_x.abs_diff(*to_x) + from_y.abs_diff(*to_y));
+    let Some((from, to)) = closest else {
+        return false;
+    };
+
+    dig_corridor(map, from, to);
+    true
+}
+
+// Generates a floor that can be walked from the entrance to every other floor tile, corridors are dug to
+// anything left cut off and the layout is thrown away and tried again if that still isn't enough
+pub fn generate_floor<G: MapGenerator, R: RngCore>(generator: &mut G, rng: &mut R) -> (Vec<Vec<TileTextureData>>, Vec<LifeformSpawn>, GenerationReport) {
+    let mut regenerations = 0;
+    loop {
+        let (mut terrain, spawns) = generator.generate(rng);
+        let mut report = validate(&terrain, &spawns);
+
+        // Each corridor joins at least one region up, so this many is always enough unless digging fails
+        let max_corridors = report.unreachable_regions.len() as u32;
+        let mut corridors_added = 0;
+        while let Some(region) = report.unreachable_regions.first() {
+            if corridors_added >= max_corridors || !connect_region(region, &mut terrain) {
+                break;
+            }
+            corridors_added += 1;
+            report = validate(&terrain, &spawns);
+        }
+        connect_walls(&mut terrain);
+        if corridors_added > 0 {
+            report = validate(&terrain, &spawns);
+        }
+
+        report.corridors_added = corridors_added;
+        report.regenerations = regenerations;
+        if report.is_valid() || regenerations >= MAX_REGENERATIONS {
+            return (terrain, spawns, report);
+        }
+        regenerations += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use rand_core::SeedableRng;
+    use crate::corridor::blit;
+    use crate::rooms::{Rect, Room};
+
+    proptest! {
+        // Every case generates four floors, some of them big ones
+        #![proptest_config(ProptestConfig::with_cases(48))]
+
+        #[test]
+        fn generated_floors_are_always_connected(seed in any::<u64>(), width in 20usize..300, height in 12usize..200) {
+            for kind in GeneratorKind::ALL {
+                let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+                let (terrain, _, report) = kind.generate_floor(width, height, &mut rng);
+                prop_assert_eq!(terrain.len(), width);
+                prop_assert!(report.is_valid(), "{} {:?}", kind, report);
+            }
+        }
+    }
+
+    #[test]
+    fn generators_have_names() {
+        for kind in GeneratorKind::ALL {
+            assert_eq!(kind.name().parse(), Ok(kind));
+        }
+        assert!("mazes".parse::<GeneratorKind>().is_err());
+        assert_eq!(GeneratorKind::for_depth(1), GeneratorKind::Rooms);
+        assert_eq!(GeneratorKind::for_depth(3), GeneratorKind::Caves);
+        assert_eq!(GeneratorKind::for_depth(5), GeneratorKind::Rooms);
+    }
+
+    #[test]
+    fn stray_rooms_get_a_corridor() {
+        let mut map = vec![vec![TileTextureData::None; 12]; 30];
+        for (x, y, width, height) in [(1, 1, 6, 5), (18, 5, 7, 6)] {
+            blit(&mut map, Room::Basic { rect: Rect::new(x, y, width, height) }.render(), x as usize, y as usize);
+        }
+        blit(&mut map, vec![vec![TileTextureData::Entrance]], 3, 3);
+        blit(&mut map, vec![vec![TileTextureData::Exit]], 21, 8);
+
+        let report = validate(&map, &[]);
+        assert!(!report.exit_reachable);
+        assert_eq!(report.unreachable_regions.len(), 1);
+
+        assert!(connect_region(&report.unreachable_regions[0], &mut map));
+        connect_walls(&mut map);
+        assert!(validate(&map, &[]).is_valid());
+    }
+}
@@ -0,0 +1,76 @@
+// Dungeon generation with no dependency on Bevy, anything that can supply an RngCore can generate a map
+mod bsp;
+mod caves;
+mod corridor;
+mod generator;
+mod profile;
+mod rooms;
+mod tile_data;
+mod tunnels;
+mod validate;
+mod walls;
+
+pub use bsp::BspGenerator;
+pub use caves::CaveGenerator;
+pub use generator::{GeneratorKind, MapGenerator, generate_floor};
+pub use profile::{DepthProfile, DungeonProfiles, GeneratorConfig};
+pub use rooms::{IntersectState, LifeformSpawn, Order, Rect, Room, RoomGenerator};
+pub use tile_data::TileTextureData;
+pub use tunnels::TunnelGenerator;
+pub use validate::{GenerationReport, validate};
+
+use rand_core::RngCore;
+
+// An empty range, like a room size with no variance in a dungeon profile, always gives min
+pub fn rand_range<R: RngCore>(min: u32, range: u32, rng: &mut R) -> u32 {
+    rng.next_u32() % range.max(1) + min
+}
+
+// Every floor gets its own seed derived from the run's, so a floor's layout doesn't depend on what happened above it
+pub fn floor_seed(run_seed: u64, depth: u32) -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    seed[..8].copy_from_slice(&run_seed.to_le_bytes());
+    seed[8..12].copy_from_slice(&depth.to_le_bytes());
+    seed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+    use rand_core::SeedableRng;
+
+    fn generate(seed: u64) -> (Vec<Vec<TileTextureData>>, Vec<LifeformSpawn>) {
+        let mut rng = ChaCha8Rng::from_seed(floor_seed(seed, 1));
+        RoomGenerator::new(100, 40).generate(&mut rng)
+    }
+
+    #[test]
+    fn floors_get_different_seeds() {
+        assert_eq!(floor_seed(42, 1), floor_seed(42, 1));
+        assert_ne!(floor_seed(42, 1), floor_seed(42, 2));
+        assert_ne!(floor_seed(42, 1), floor_seed(43, 1));
+    }
+
+    #[test]
+    fn same_seed_same_map() {
+        assert_eq!(generate(42), generate(42));
+    }
+
+    #[test]
+    fn maps_fill_the_requested_size() {
+        let (terrain, spawns) = generate(7);
+        assert_eq!(terrain.len(), 100);
+        assert!(terrain.iter().all(|column| column.len() == 40));
+        assert!(spawns.iter().all(|spawn| spawn.x < 100 && spawn.y < 40));
+        assert_eq!(spawns.iter().filter(|spawn| spawn.tile_data == TileTextureData::Player).count(), 1);
+    }
+
+    #[test]
+    fn rooms_reach_past_the_first_256_tiles() {
+        let mut rng = ChaCha8Rng::from_seed(floor_seed(7, 1));
+        let (terrain, _) = RoomGenerator::new(600, 400).generate(&mut rng);
+        assert!(terrain[300..].iter().any(|column| column.contains(&TileTextureData::Floor)));
+        assert!(terrain.iter().any(|column| column[300..].contains(&TileTextureData::Floor)));
+    }
+}
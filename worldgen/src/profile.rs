@@ -0,0 +1,135 @@
+use rand_core::RngCore;
+use crate::bsp::BspGenerator;
+use crate::caves::CaveGenerator;
+use crate::generator::{GeneratorKind, generate_floor};
+use crate::rooms::{LifeformSpawn, RoomGenerator};
+use crate::tile_data::TileTextureData;
+use crate::tunnels::TunnelGenerator;
+use crate::validate::GenerationReport;
+
+// One of the generators along with the settings to run it with, anything left out keeps its default
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "generator", rename_all = "lowercase")]
+pub enum GeneratorConfig {
+    Rooms(RoomGenerator),
+    Bsp(BspGenerator),
+    Caves(CaveGenerator),
+    Tunnels(TunnelGenerator)
+}
+
+impl GeneratorConfig {
+    pub fn kind(&self) -> GeneratorKind {
+        match self {
+            GeneratorConfig::Rooms(_) => GeneratorKind::Rooms,
+            GeneratorConfig::Bsp(_) => GeneratorKind::Bsp,
+            GeneratorConfig::Caves(_) => GeneratorKind::Caves,
+            GeneratorConfig::Tunnels(_) => GeneratorKind::Tunnels
+        }
+    }
+
+    pub fn generate_floor<R: RngCore>(&self, map_width: usize, map_height: usize, rng: &mut R) -> (Vec<Vec<TileTextureData>>, Vec<LifeformSpawn>, GenerationReport) {
+        match self.clone() {
+            GeneratorConfig::Rooms(mut generator) => {
+                (generator.map_width, generator.map_height) = (map_width, map_height);
+                generate_floor(&mut generator, rng)
+            }
+            GeneratorConfig::Bsp(mut generator) => {
+                (generator.map_width, generator.map_height) = (map_width, map_height);
+                generate_floor(&mut generator, rng)
+            }
+            GeneratorConfig::Caves(mut generator) => {
+                (generator.map_width, generator.map_height) = (map_width, map_height);
+                generate_floor(&mut generator, rng)
+            }
+            GeneratorConfig::Tunnels(mut generator) => {
+                (generator.map_width, generator.map_height) = (map_width, map_height);
+                generate_floor(&mut generator, rng)
+            }
+        }
+    }
+}
+
+impl From<GeneratorKind> for GeneratorConfig {
+    fn from(kind: GeneratorKind) -> Self {
+        match kind {
+            GeneratorKind::Rooms => GeneratorConfig::Rooms(RoomGenerator::default()),
+            GeneratorKind::Bsp => GeneratorConfig::Bsp(BspGenerator::default()),
+            GeneratorKind::Caves => GeneratorConfig::Caves(CaveGenerator::default()),
+            GeneratorKind::Tunnels => GeneratorConfig::Tunnels(TunnelGenerator::default())
+        }
+    }
+}
+
+// The generators used from a depth downwards, until a deeper profile takes over
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct DepthProfile {
+    pub from_depth: u32,
+    // Each floor takes the next one in turn
+    pub generators: Vec<GeneratorConfig>
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "bevy", derive(bevy::asset::Asset, bevy::reflect::TypePath))]
+pub struct DungeonProfiles {
+    pub profiles: Vec<DepthProfile>
+}
+
+impl Default for DungeonProfiles {
+    // Every generator with its default settings, in the same order GeneratorKind::for_depth gives them
+    fn default() -> Self {
+        DungeonProfiles {
+            profiles: vec![DepthProfile {
+                from_depth: 1,
+                generators: GeneratorKind::ALL.into_iter().map(GeneratorConfig::from).collect()
+            }]
+        }
+    }
+}
+
+impl DungeonProfiles {
+    // Depths no profile covers fall back on the default generators
+    pub fn generator_for(&self, depth: u32) -> GeneratorConfig {
+        self.profiles.iter()
+            .filter(|profile| profile.from_depth <= depth && !profile.generators.is_empty())
+            .max_by_key(|profile| profile.from_depth)
+            .map(|profile| profile.generators[(depth - profile.from_depth) as usize % profile.generators.len()].clone())
+            .unwrap_or_else(|| GeneratorKind::for_depth(depth).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES: &str = r#"{
+        "profiles": [
+            { "from_depth": 1, "generators": [{ "generator": "rooms", "room_count": 20, "max_enemies_per_room": 1 }] },
+            { "from_depth": 3, "generators": [{ "generator": "caves", "fill_percent": 40 }, { "generator": "tunnels" }] }
+        ]
+    }"#;
+
+    #[test]
+    fn profiles_pick_generators_by_depth() {
+        let profiles: DungeonProfiles = serde_json::from_str(PROFILES).unwrap();
+        let GeneratorConfig::Rooms(rooms) = profiles.generator_for(2) else {
+            panic!("the first two floors should be rooms");
+        };
+        assert_eq!((rooms.room_count, rooms.max_enemies_per_room, rooms.mean_room_width), (20, 1, 7));
+        let GeneratorConfig::Caves(caves) = profiles.generator_for(3) else {
+            panic!("the third floor should be caves");
+        };
+        assert_eq!((caves.fill_percent, caves.smoothing_passes), (40, 5));
+        assert_eq!(profiles.generator_for(4).kind(), GeneratorKind::Tunnels);
+        assert_eq!(profiles.generator_for(5).kind(), GeneratorKind::Caves);
+    }
+
+    #[test]
+    fn default_profiles_match_the_built_in_order() {
+        let profiles = DungeonProfiles::default();
+        for depth in 1..10 {
+            assert_eq!(profiles.generator_for(depth).kind(), GeneratorKind::for_depth(depth));
+        }
+        let empty = DungeonProfiles { profiles: vec![] };
+        assert_eq!(empty.generator_for(2).kind(), GeneratorKind::Bsp);
+    }
+}
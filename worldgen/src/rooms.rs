@@ -0,0 +1,653 @@
+use rand_core::RngCore;
+use crate::rand_range;
+use crate::corridor::{blit, dig_corridor};
+use crate::generator::{MapGenerator, spawn_at};
+use crate::tile_data::TileTextureData;
+
+#[derive(Default, Debug)]
+#[derive(PartialEq)]
+pub enum IntersectState {
+    #[default]
+    None,
+    Full,
+    Partial
+}
+
+// Only meaningful for full intersections, FirstGreater means the first room is enclosed by the second
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Order {
+    FirstGreater,
+    SecondGreater,
+    Same
+}
+
+impl Order {
+    // The same relationship with the rooms compared the other way round
+    pub fn flip(self) -> Order {
+        match self {
+            Order::FirstGreater => Order::SecondGreater,
+            Order::SecondGreater => Order::FirstGreater,
+            Order::Same => Order::Same
+        }
+    }
+}
+
+#[derive(Default, Eq, PartialEq, Clone, Debug, Copy)]
+pub struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height
+        }
+    }
+
+    // One past the last column, saturating rather than wrapping for rooms right at the edge of u32
+    fn far_x(&self) -> u32 {
+        self.x.saturating_add(self.width)
+    }
+
+    fn far_y(&self) -> u32 {
+        self.y.saturating_add(self.height)
+    }
+
+    // The floor inside the walls, as (start, length), rooms too thin to have any still get a single tile
+    fn inner_x(&self) -> (u32, u32) {
+        (self.x.saturating_add(1), self.width.saturating_sub(2).max(1))
+    }
+
+    fn inner_y(&self) -> (u32, u32) {
+        (self.y.saturating_add(1), self.height.saturating_sub(2).max(1))
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Room {
+    Basic {
+        rect: Rect
+    },
+    Complex {
+        rooms: Vec<Rect>,
+        aabb: Rect
+    }
+}
+
+impl Room {
+
+    pub fn get_random_point_in<R: RngCore>(&self, rng: &mut R) -> (usize, usize) {
+        match self {
+            Room::Basic { rect } => {
+                Room::random_point_in_rect(rect, rng)
+            }
+            Room::Complex { rooms, .. } => {
+                let room_num = rand_range(0, rooms.len() as u32, rng) as usize;
+                Room::random_point_in_rect(&rooms[room_num], rng)
+            }
+        }
+    }
+
+    fn random_point_in_rect<R: RngCore>(rect: &Rect, rng: &mut R) -> (usize, usize) {
+        let (inner_x, inner_width) = rect.inner_x();
+        let (inner_y, inner_height) = rect.inner_y();
+        (rand_range(inner_x, inner_width, rng) as usize,
+         rand_range(inner_y, inner_height, rng) as usize)
+    }
+
+    fn render_rect(rect: Rect) -> Vec<Vec<TileTextureData>> {
+        let mut output = vec![];
+        for x in 0..rect.width as usize {
+            if x == 0 || x == rect.width.saturating_sub(1) as usize {
+                output.push(vec![TileTextureData::Wall{
+                    connects_north: false,
+                    connects_south: false,
+                    connects_east: false,
+                    connects_west: false
+                }; rect.height as usize]);
+            } else {
+                output.push(vec![TileTextureData::Floor; rect.height as usize]);
+                output[x][0] = TileTextureData::Wall {
+                    connects_north: false,
+                    connects_south: false,
+                    connects_east: false,
+                    connects_west: false
+                };
+                output[x][rect.height.saturating_sub(1) as usize] = TileTextureData::Wall {
+                    connects_north: false,
+                    connects_south: false,
+                    connects_east: false,
+                    connects_west: false
+                };
+            }
+        }
+        output
+    }
+
+    pub fn render(self) -> Vec<Vec<TileTextureData>> {
+        // For Complex Rooms:
+        // Merge all the rooms into a single Vec of TileData (Only making Floors)
+        // Then find the edges of the Floors and make Walls
+        // Then the return value will be BLIT onto the map afterwards
+        match self {
+            Room::Basic { rect } => {
+                Room::render_rect(rect)
+            }
+            Room::Complex { rooms, aabb } => {
+                let mut output = vec![];
+                for _ in 0..aabb.width as usize {
+                    output.push(vec![TileTextureData::None; aabb.height as usize]);
+                }
+
+                for room in rooms {
+                    let rendered_room = Room::render_rect(room);
+                    let x_offset = (room.x - aabb.x) as usize;
+                    let y_offset = (room.y - aabb.y) as usize;
+
+                    blit(&mut output, rendered_room, x_offset, y_offset);
+                }
+                output
+            }
+        }
+    }
+
+    fn calculate_aabb(rect1: Rect, rect2: Rect) -> Rect {
+        let far_x = std::cmp::max(rect1.far_x(), rect2.far_x());
+        let far_y = std::cmp::max(rect1.far_y(), rect2.far_y());
+        let close_x = std::cmp::min(rect1.x, rect2.x);
+        let close_y = std::cmp::min(rect1.y, rect2.y);
+
+        Rect {
+            x: close_x,
+            y: close_y,
+            width: far_x - close_x,
+            height: far_y - close_y
+        }
+    }
+
+    pub fn combine_rooms(this: Room, that: Room) -> Room {
+        match (this.clone(), that.clone()) {
+            (Room::Basic {rect: this_rect}, Room::Basic{rect: that_rect}) => {
+                Room::Complex {
+                    rooms: vec![this_rect, that_rect],
+                    aabb: Room::calculate_aabb(this_rect, that_rect)
+                }
+            }
+            (Room::Basic {rect}, Room::Complex {mut rooms, aabb}) => {
+                rooms.push(rect);
+                let aabb = Room::calculate_aabb(rect, aabb);
+                Room::Complex {
+                    rooms,
+                    aabb
+                }
+            }
+            (Room::Complex {mut rooms, aabb}, Room::Basic {rect}) => {
+                rooms.push(rect);
+                let aabb = Room::calculate_aabb(rect, aabb);
+                Room::Complex {
+                    rooms,
+                    aabb
+                }
+            }
+            (Room::Complex{rooms: mut this_rooms, aabb: this_aabb}, Room::Complex{rooms: mut that_rooms, aabb: that_aabb}) => {
+                this_rooms.append(&mut that_rooms);
+                let aabb = Room::calculate_aabb(this_aabb, that_aabb);
+                Room::Complex {
+                    rooms: this_rooms,
+                    aabb
+                }
+            }
+        }
+    }
+
+    fn edge_intersects(edge1_start: u32, edge1_end: u32, edge2_start: u32, edge2_end: u32) -> (IntersectState, Order) {
+        // intersects if start/end of either edge is within the range of the other edge
+        // fully intersects if start & end of either edge is fully within the range of the other edge
+        if edge1_end < edge2_start || edge2_end < edge1_start {
+            return (IntersectState::None, Order::Same);
+        }
+
+        let first_inside = edge1_start >= edge2_start && edge1_end <= edge2_end;
+        let second_inside = edge2_start >= edge1_start && edge2_end <= edge1_end;
+        match (first_inside, second_inside) {
+            (true, true) => (IntersectState::Full, Order::Same),
+            (true, false) => (IntersectState::Full, Order::FirstGreater),
+            (false, true) => (IntersectState::Full, Order::SecondGreater),
+            (false, false) => (IntersectState::Partial, Order::Same)
+        }
+    }
+
+    fn basic_basic_intersection(this: Room, that: Room) -> (IntersectState, Order) {
+        match (this, that) {
+            (Room::Basic{ rect: this_rect }, Room::Basic{rect: that_rect}) => {
+                let mut total_intersection = IntersectState::None;
+                let mut total_order = Order::Same;
+
+                let (x_intersection, x_order) = Room::edge_intersects(this_rect.x.saturating_add(1), this_rect.far_x().saturating_sub(1), that_rect.x.saturating_add(1), that_rect.far_x().saturating_sub(1));
+                let (y_intersection, y_order) = Room::edge_intersects(this_rect.y.saturating_add(1), this_rect.far_y().saturating_sub(1), that_rect.y.saturating_add(1), that_rect.far_y().saturating_sub(1));
+
+                match (x_intersection, y_intersection) {
+                    (IntersectState::Partial, IntersectState::Partial) => {
+                        total_intersection = IntersectState::Partial;
+                    }
+                    (IntersectState::Partial, IntersectState::Full) => {
+                        total_intersection = IntersectState::Partial;
+                    }
+                    (IntersectState::Full, IntersectState::Partial) => {
+                        total_intersection = IntersectState::Partial;
+                    }
+                    (IntersectState::Full, IntersectState::Full) => {
+                        match (x_order, y_order) {
+                            (Order::Same, order) | (order, Order::Same) => {
+                                total_intersection = IntersectState::Full;
+                                total_order = order;
+                            }
+                            (x_order, y_order) if x_order == y_order => {
+                                total_intersection = IntersectState::Full;
+                                total_order = x_order;
+                            }
+                            // One is wider and the other is taller, they cross over without either enclosing the other
+                            (_, _) => {
+                                total_intersection = IntersectState::Partial;
+                            }
+                        }
+                    }
+                    // Do nothing on purpose
+                    _ => {}
+                }
+
+                (total_intersection, total_order)
+            }
+            _ => panic!()
+        }
+    }
+
+    fn basic_complex_intersection(this: Room, that: Room) -> (IntersectState, Order) {
+        match (this, that) {
+            (Room::Basic{rect: this_rect}, Room::Complex{rooms: that_rooms, aabb: that_aabb}) => {
+                let (intersection, _) = Room::basic_basic_intersection(Room::Basic{rect: this_rect}, Room::Basic{rect: that_aabb});
+
+                // If the AABB doesn't intersect, no need to do more work
+                if intersection == IntersectState::None {
+                    return (IntersectState::None, Order::Same);
+                }
+
+                // Find all rooms that intersect with the compared basic room
+                let mut intersects_any = false;
+                let mut encloses_all = true;
+                for that_room in that_rooms {
+                    let (inner_intersection, inner_order) = Room::basic_basic_intersection(Room::Basic{rect: this_rect}, Room::Basic{rect: that_room});
+                    match (inner_intersection, inner_order) {
+                        // Sitting entirely inside one of the rooms means it's inside the complex room too
+                        (IntersectState::Full, Order::FirstGreater | Order::Same) => {
+                            return (IntersectState::Full, Order::FirstGreater);
+                        }
+                        (IntersectState::Full, Order::SecondGreater) => {
+                            intersects_any = true;
+                        }
+                        (IntersectState::Partial, _) => {
+                            intersects_any = true;
+                            encloses_all = false;
+                        }
+                        (IntersectState::None, _) => {
+                            encloses_all = false;
+                        }
+                    }
+                }
+
+                if encloses_all {
+                    (IntersectState::Full, Order::SecondGreater)
+                } else if intersects_any {
+                    (IntersectState::Partial, Order::Same)
+                } else {
+                    (IntersectState::None, Order::Same)
+                }
+            }
+            _ => panic!()
+        }
+    }
+
+    fn complex_complex_intersection(this: Room, that: Room) -> (IntersectState, Order) {
+        match (this, that.clone()) {
+            (Room::Complex{rooms: this_rooms, aabb: this_aabb}, Room::Complex{aabb: that_aabb, ..}) => {
+                let (aabb_intersection, _) = Room::basic_basic_intersection(Room::Basic{rect: this_aabb}, Room::Basic{rect: that_aabb});
+
+                // If the AABB doesn't intersect, no need  to do more work
+                if aabb_intersection != IntersectState::None {
+                    // Do the more complex work
+                    for room in this_rooms {
+                        // If there's any intersection between any 2 rooms between the two complex rooms, then it's an intersection!
+                        let (inner_intersection, ..) = Room::basic_complex_intersection(Room::Basic{rect: room}, that.clone());
+                        if inner_intersection != IntersectState::None {
+                            return (IntersectState::Partial, Order::Same);
+                        }
+                    }
+                }
+
+                (IntersectState::None, Order::Same)
+            }
+            _ => panic!()
+        }
+    }
+
+    pub fn intersects(&self, other: Room) -> (IntersectState, Order) {
+        match (self, &other) {
+            (Room::Basic{..}, Room::Basic{..}) => {Room::basic_basic_intersection(self.clone(), other)}
+            (Room::Basic{..}, Room::Complex{..}) => {Room::basic_complex_intersection(self.clone(), other)}
+            (Room::Complex{..}, Room::Basic{..}) => {
+                // Compared the other way round, so the order has to be turned back around
+                let (intersection, order) = Room::basic_complex_intersection(other, self.clone());
+                (intersection, order.flip())
+            }
+            (Room::Complex{..}, Room::Complex{..}) => {Room::complex_complex_intersection(self.clone(), other)}
+        }
+    }
+}
+
+// When we generate rooms, we'll check if the new room intersects with any old rooms
+// If it does, we'll combine it with the room it intersects with... If it combines with multiple Complex Rooms... uh... try again?
+// Then the front end renders it into textures, woo!
+
+// Somewhere a lifeform starts out on a generated map, with its starting stats
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct LifeformSpawn {
+    pub tile_data: TileTextureData,
+    pub x: usize,
+    pub y: usize,
+    pub health: u32,
+    pub strength: u32,
+    pub defense: u32,
+    pub level: u32,
+    pub experience: u32
+}
+
+// The map size and rooms come from the floor being generated, only the rest can be set by a dungeon profile
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RoomGenerator {
+    pub room_count: u8,
+    #[serde(skip)]
+    pub map_width: usize,
+    #[serde(skip)]
+    pub map_height: usize,
+    #[serde(skip)]
+    pub rooms: Vec<Room>,
+    pub mean_room_width: u32,
+    pub mean_room_height: u32,
+    pub width_variance: u32,
+    pub height_variance: u32,
+    pub max_enemies_per_room: u32
+}
+
+impl RoomGenerator {
+    // Whether the biggest room this generator makes fits on its map with space to move it around
+    pub fn fits(&self) -> bool {
+        self.map_width > (self.mean_room_width + self.width_variance) as usize
+            && self.map_height > (self.mean_room_height + self.height_variance) as usize
+    }
+
+    // The room layout every floor uses, sized to the map
+    pub fn new(map_width: usize, map_height: usize) -> RoomGenerator {
+        RoomGenerator {
+            room_count: 50,
+            map_width,
+            map_height,
+            rooms: vec![],
+            mean_room_width: 7,
+            mean_room_height: 6,
+            width_variance: 3,
+            height_variance: 2,
+            max_enemies_per_room: 2,
+        }
+    }
+
+    fn generate_random_entity<R: RngCore>(rng: &mut R, room: Room, entity_type: TileTextureData, output: &mut Vec<LifeformSpawn>) {
+        let (x, y) = room.get_random_point_in(rng);
+        spawn_at(rng, x, y, entity_type, output);
+    }
+
+    fn generate_corridor<R: RngCore>(rng: &mut R, from_room: Room, to_room: Room, map: &mut [Vec<TileTextureData>]) {
+        let from = from_room.get_random_point_in(rng);
+        let to = to_room.get_random_point_in(rng);
+        dig_corridor(map, from, to);
+    }
+}
+
+impl Default for RoomGenerator {
+    fn default() -> Self {
+        RoomGenerator::new(0, 0)
+    }
+}
+
+impl MapGenerator for RoomGenerator {
+    fn generate<R: RngCore>(&mut self, rng: &mut R) -> (Vec<Vec<TileTextureData>>, Vec<LifeformSpawn>) {
+        self.rooms.clear();
+        for _ in 0..self.room_count {
+            let calc_width = rand_range(self.mean_room_width, self.width_variance, rng);
+            let calc_height = rand_range(self.mean_room_height, self.height_variance, rng);
+            // Rooms that can't fit on the map at all are skipped rather than wrapping around
+            let map_width = u32::try_from(self.map_width).unwrap_or(u32::MAX);
+            let map_height = u32::try_from(self.map_height).unwrap_or(u32::MAX);
+            let (Some(x_range), Some(y_range)) = (map_width.checked_sub(calc_width), map_height.checked_sub(calc_height)) else {
+                continue;
+            };
+            if x_range == 0 || y_range == 0 {
+                continue;
+            }
+            let calc_x = rand_range(0, x_range, rng);
+            let calc_y = rand_range(0, y_range, rng);
+
+            let mut new_room = Room::Basic{
+                rect: Rect {
+                    x: calc_x,
+                    y: calc_y,
+                    width: calc_width,
+                    height: calc_height
+                }
+            };
+
+            let mut should_add = true;
+            let mut remove_idx: Vec<usize> = vec![];
+            for idx in 0..self.rooms.len() {
+                let room = &self.rooms[idx];
+                let (intersection_state, order) = room.intersects(new_room.clone());
+                match intersection_state {
+                    IntersectState::None => {
+                        continue
+                    }
+                    IntersectState::Full => {
+                        if order == Order::FirstGreater {
+                            should_add = true;
+                            remove_idx.push(idx);
+                        } else {
+                            should_add = false;
+                            break;
+                        }
+                    }
+                    IntersectState::Partial => {
+                        should_add = true;
+                        remove_idx.push(idx);
+                        new_room = Room::combine_rooms(room.clone(), new_room.clone());
+                    }
+                }
+            }
+            for (idx_removed, idx_to_remove) in remove_idx.into_iter().enumerate() {
+                self.rooms.remove(idx_to_remove - idx_removed);
+            }
+            if should_add {
+                self.rooms.push(new_room);
+            }
+        }
+
+        let mut output: Vec<Vec<TileTextureData>> = vec![vec![TileTextureData::None; self.map_height]; self.map_width];
+        let mut output_entities: Vec<LifeformSpawn> = vec![];
+
+        for idx in 0..self.rooms.len() {
+            // Room Rendering
+            let room = &self.rooms[idx];
+            let rendered_room = room.clone().render();
+            match room {
+                Room::Basic { rect } => {
+                    blit(&mut output, rendered_room, rect.x as usize, rect.y as usize);
+                    let enemies_in_this_room = rand_range(0, self.max_enemies_per_room, rng);
+                    for _ in 0..enemies_in_this_room {
+                        Self::generate_random_entity(rng, Room::Basic{rect: *rect}, TileTextureData::Enemy, &mut output_entities);
+                    }
+                }
+                Room::Complex { aabb, rooms } => {
+                    blit(&mut output, rendered_room, aabb.x as usize, aabb.y as usize);
+                    for idx_2 in 0..rooms.len() {
+                        let room = rooms[idx_2];
+                        let enemies_in_this_room = rand_range(0, self.max_enemies_per_room, rng);
+                        for _ in 0..enemies_in_this_room {
+                            Self::generate_random_entity(rng, Room::Basic{rect: room}, TileTextureData::Enemy, &mut output_entities);
+                        }
+                        if idx_2 != 0 {
+                            Self::generate_corridor(rng, Room::Basic{rect: rooms[idx_2 - 1]}, Room::Basic{rect: rooms[idx_2]}, &mut output);
+                        }
+                    }
+                }
+            }
+
+            // Entry/Exit generation
+            if idx == 0 {
+                let (entrance_x, entrance_y) = room.get_random_point_in(rng);
+                blit(&mut output, vec![vec![TileTextureData::Entrance]], entrance_x, entrance_y);
+                spawn_at(rng, entrance_x, entrance_y, TileTextureData::Player, &mut output_entities);
+            }
+            if idx == self.rooms.len() - 1 {
+                let (exit_x, exit_y) = room.get_random_point_in(rng);
+                blit(&mut output, vec![vec![TileTextureData::Exit]], exit_x, exit_y);
+            }
+
+            // Corridor Generation
+            if idx > 0 {
+                // Generate a corridor from this room to the previous room
+                Self::generate_corridor(rng, self.rooms[idx - 1].clone(), room.clone(), &mut output);
+            }
+        }
+
+        (output, output_entities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use rand_core::SeedableRng;
+
+    // Spread well past the old u8 limit, up against the edge of u32 too
+    fn rect() -> impl Strategy<Value = Rect> {
+        let start = prop_oneof![0u32..150, 200u32..2000, (u32::MAX - 100)..u32::MAX];
+        (start.clone(), start, 3u32..40, 3u32..40).prop_map(|(x, y, width, height)| Rect { x, y, width, height })
+    }
+
+    fn complex_room() -> impl Strategy<Value = Room> {
+        prop::collection::vec(rect(), 2..5).prop_map(|rects| {
+            rects.into_iter().map(|rect| Room::Basic { rect }).reduce(Room::combine_rooms).unwrap()
+        })
+    }
+
+    fn room() -> impl Strategy<Value = Room> {
+        prop_oneof![rect().prop_map(|rect| Room::Basic { rect }), complex_room()]
+    }
+
+    // The span intersections are measured over, matching basic_basic_intersection
+    fn span(start: u32, length: u32) -> (u32, u32) {
+        (start.saturating_add(1), start.saturating_add(length).saturating_sub(1))
+    }
+
+    fn overlaps(a: (u32, u32), b: (u32, u32)) -> bool {
+        a.0 <= b.1 && b.0 <= a.1
+    }
+
+    fn encloses(outer: (u32, u32), inner: (u32, u32)) -> bool {
+        outer.0 <= inner.0 && inner.1 <= outer.1
+    }
+
+    fn rects_of(room: &Room) -> Vec<Rect> {
+        match room {
+            Room::Basic { rect } => vec![*rect],
+            Room::Complex { rooms, .. } => rooms.clone()
+        }
+    }
+
+    fn aabb_encloses(aabb: &Rect, rect: &Rect) -> bool {
+        aabb.x <= rect.x && aabb.y <= rect.y
+            && rect.far_x() <= aabb.far_x()
+            && rect.far_y() <= aabb.far_y()
+    }
+
+    proptest! {
+        #[test]
+        fn basic_intersection_matches_geometry(a in rect(), b in rect()) {
+            let (a_x, a_y) = (span(a.x, a.width), span(a.y, a.height));
+            let (b_x, b_y) = (span(b.x, b.width), span(b.y, b.height));
+            let (state, order) = Room::Basic { rect: a }.intersects(Room::Basic { rect: b });
+
+            if !overlaps(a_x, b_x) || !overlaps(a_y, b_y) {
+                prop_assert_eq!(state, IntersectState::None);
+            } else if a_x == b_x && a_y == b_y {
+                prop_assert_eq!((state, order), (IntersectState::Full, Order::Same));
+            } else if encloses(b_x, a_x) && encloses(b_y, a_y) {
+                prop_assert_eq!((state, order), (IntersectState::Full, Order::FirstGreater));
+            } else if encloses(a_x, b_x) && encloses(a_y, b_y) {
+                prop_assert_eq!((state, order), (IntersectState::Full, Order::SecondGreater));
+            } else {
+                prop_assert_eq!(state, IntersectState::Partial);
+            }
+        }
+
+        #[test]
+        fn intersection_is_symmetric(a in room(), b in room()) {
+            let (state, order) = a.intersects(b.clone());
+            let (flipped_state, flipped_order) = b.intersects(a);
+            prop_assert_eq!(&state, &flipped_state);
+            if state == IntersectState::Full {
+                prop_assert_eq!(order, flipped_order.flip());
+            }
+        }
+
+        #[test]
+        fn intersection_agrees_with_the_rooms_inside(a in room(), b in room()) {
+            let any_overlap = rects_of(&a).iter().any(|a_rect| rects_of(&b).iter().any(|b_rect| {
+                overlaps(span(a_rect.x, a_rect.width), span(b_rect.x, b_rect.width))
+                    && overlaps(span(a_rect.y, a_rect.height), span(b_rect.y, b_rect.height))
+            }));
+            let (state, _) = a.intersects(b);
+            prop_assert_eq!(state != IntersectState::None, any_overlap);
+        }
+
+        #[test]
+        fn aabb_encloses_both_rects(a in rect(), b in rect()) {
+            let aabb = Room::calculate_aabb(a, b);
+            prop_assert!(aabb_encloses(&aabb, &a));
+            prop_assert!(aabb_encloses(&aabb, &b));
+        }
+
+        #[test]
+        fn combined_rooms_keep_every_rect_inside_the_aabb(a in room(), b in room()) {
+            let rect_count = rects_of(&a).len() + rects_of(&b).len();
+            let combined = Room::combine_rooms(a, b);
+            let Room::Complex { rooms, aabb } = combined else {
+                return Err(TestCaseError::fail("combining rooms should always make a complex room"));
+            };
+            prop_assert_eq!(rooms.len(), rect_count);
+            prop_assert!(rooms.iter().all(|rect| aabb_encloses(&aabb, rect)));
+        }
+
+        #[test]
+        fn generation_never_panics(seed in any::<u64>()) {
+            let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+            let (terrain, _) = RoomGenerator::new(100, 40).generate(&mut rng);
+            prop_assert_eq!(terrain.len(), 100);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+#[derive(Default, Eq, Hash, PartialEq, Clone, Debug, Copy, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "bevy", derive(bevy::asset::Asset, bevy::reflect::TypePath))]
+#[serde(tag = "type")]
+pub enum TileTextureData {
+    #[default]
+    None,
+    Floor,
+    Corridor {
+        start: bool
+    },
+    Player,
+    Enemy,
+    Wall {
+        #[serde(alias="north")]
+        connects_north: bool,
+        #[serde(alias="south")]
+        connects_south: bool,
+        #[serde(alias="east")]
+        connects_east: bool,
+        #[serde(alias="west")]
+        connects_west: bool
+    },
+    Entrance,
+    Exit
+}
+
+impl TileTextureData {
+    pub fn can_replace(&self, old: TileTextureData) -> bool {
+        match (self, old) {
+            (TileTextureData::Corridor{start},TileTextureData::Corridor{start: old_start}) => {
+                *start || !old_start
+            }
+            (_,TileTextureData::Entrance) => {false}
+            (_,TileTextureData::Exit) => {false}
+            (TileTextureData::Corridor{..},_) => {true}
+            (TileTextureData::Floor, TileTextureData::Corridor{..}) => {false}
+            (TileTextureData::Wall{..}, TileTextureData::Corridor{..}) => {false}
+            (TileTextureData::Wall{..}, TileTextureData::Floor) => {false}
+            (TileTextureData::Enemy, TileTextureData::Player) => {false}
+            (TileTextureData::Player, TileTextureData::Enemy) => {true}
+            (TileTextureData::Enemy, _) => {panic!("Enemy replacing non-entity")}
+            (TileTextureData::Player, _) => {panic!("Player replacing non-entity")}
+            (_,_) => {true}
+        }
+    }
+
+    pub fn makes_walls(&self) -> bool {
+        matches!(self, TileTextureData::Corridor{..} | TileTextureData::Floor)
+    }
+
+    pub fn connects_to_walls(&self) -> bool {
+        matches!(self, TileTextureData::Wall{..})
+    }
+
+    pub fn repair_tile_data(&self) -> TileTextureData {
+        match self {
+            // A wall on its own is drawn as a horizontal one
+            TileTextureData::Wall {connects_north: false, connects_south: false, connects_east: false, connects_west: false} => {
+                TileTextureData::Wall {
+                    connects_north: false,
+                    connects_east: true,
+                    connects_south: false,
+                    connects_west: true
+                }
+            }
+            _ => {
+                *self
+            }
+        }
+    }
+
+    pub fn is_passable(&self) -> bool {
+        match self {
+            TileTextureData::None => {false}
+            TileTextureData::Floor => {true}
+            TileTextureData::Corridor { .. } => {true}
+            TileTextureData::Player => {false}
+            TileTextureData::Enemy => {false}
+            TileTextureData::Wall { .. } => {false}
+            TileTextureData::Entrance => {true}
+            TileTextureData::Exit => {true}
+        }
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        matches!(self, TileTextureData::Wall { .. })
+    }
+}
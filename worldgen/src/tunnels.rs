@@ -0,0 +1,73 @@
+use rand_core::RngCore;
+use crate::generator::{MapGenerator, populate};
+use crate::rand_range;
+use crate::rooms::LifeformSpawn;
+use crate::tile_data::TileTextureData;
+use crate::walls::EMPTY_WALL;
+
+// A drunkard's walk, something stumbles around from the middle of the map digging as it goes
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TunnelGenerator {
+    #[serde(skip)]
+    pub map_width: usize,
+    #[serde(skip)]
+    pub map_height: usize,
+    // How much of the map gets dug out before the walk stops, out of 100
+    pub floor_percent: u32,
+    pub floor_per_enemy: u32
+}
+
+impl TunnelGenerator {
+    // Long enough that a walk stuck in a dug out corner still finishes, short enough that it can't go on forever
+    const STEPS_PER_TILE: usize = 50;
+
+    pub fn new(map_width: usize, map_height: usize) -> TunnelGenerator {
+        TunnelGenerator {
+            map_width,
+            map_height,
+            floor_percent: 35,
+            floor_per_enemy: 60
+        }
+    }
+}
+
+impl Default for TunnelGenerator {
+    fn default() -> Self {
+        TunnelGenerator::new(0, 0)
+    }
+}
+
+impl MapGenerator for TunnelGenerator {
+    fn generate<R: RngCore>(&mut self, rng: &mut R) -> (Vec<Vec<TileTextureData>>, Vec<LifeformSpawn>) {
+        let (width, height) = (self.map_width, self.map_height);
+        let mut output = vec![vec![EMPTY_WALL; height]; width];
+        // The walk stays off the edge of the map so the tunnels always have a wall around them
+        if width < 3 || height < 3 {
+            return (output, vec![]);
+        }
+
+        let target = ((width - 2) * (height - 2) * self.floor_percent as usize / 100).max(1);
+        let (mut x, mut y) = (width / 2, height / 2);
+        output[x][y] = TileTextureData::Floor;
+        let mut dug = 1;
+        let mut steps = 0;
+        while dug < target && steps < target * Self::STEPS_PER_TILE {
+            steps += 1;
+            match rand_range(0, 4, rng) {
+                0 if y + 2 < height => y += 1,
+                1 if y > 1 => y -= 1,
+                2 if x + 2 < width => x += 1,
+                3 if x > 1 => x -= 1,
+                _ => continue
+            }
+            if output[x][y] != TileTextureData::Floor {
+                output[x][y] = TileTextureData::Floor;
+                dug += 1;
+            }
+        }
+
+        let output_entities = populate(&mut output, self.floor_per_enemy, rng);
+        (output, output_entities)
+    }
+}
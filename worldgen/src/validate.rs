@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use crate::rooms::LifeformSpawn;
+use crate::tile_data::TileTextureData;
+
+// What a generated floor looked like to the validator, and what it took to get it there
+#[derive(Default, Eq, PartialEq, Clone, Debug)]
+pub struct GenerationReport {
+    pub entrance: Option<(usize, usize)>,
+    pub exit: Option<(usize, usize)>,
+    pub exit_reachable: bool,
+    // Walkable tiles the entrance can't reach, grouped into regions that can reach each other
+    pub unreachable_regions: Vec<Vec<(usize, usize)>>,
+    // Spawns standing on something that can't be walked on, or off the map entirely
+    pub blocked_spawns: Vec<LifeformSpawn>,
+    pub corridors_added: u32,
+    pub regenerations: u32
+}
+
+impl GenerationReport {
+    pub fn is_valid(&self) -> bool {
+        self.entrance.is_some()
+            && self.exit_reachable
+            && self.unreachable_regions.is_empty()
+            && self.blocked_spawns.is_empty()
+    }
+}
+
+pub(crate) fn find_tile(terrain: &[Vec<TileTextureData>], wanted: TileTextureData) -> Option<(usize, usize)> {
+    terrain.iter().enumerate().find_map(|(x, column)| {
+        column.iter().position(|tile_data| *tile_data == wanted).map(|y| (x, y))
+    })
+}
+
+// Every walkable tile connected to the start, moving the way lifeforms do
+pub(crate) fn flood_fill(terrain: &[Vec<TileTextureData>], start: (usize, usize), visited: &mut [Vec<bool>]) -> Vec<(usize, usize)> {
+    let mut region = vec![];
+    let mut queue = VecDeque::from([start]);
+    visited[start.0][start.1] = true;
+
+    while let Some((x, y)) = queue.pop_front() {
+        region.push((x, y));
+        let mut neighbors = vec![(x + 1, y), (x, y + 1)];
+        if x > 0 {
+            neighbors.push((x - 1, y));
+        }
+        if y > 0 {
+            neighbors.push((x, y - 1));
+        }
+        for (next_x, next_y) in neighbors {
+            if next_x < terrain.len() && next_y < terrain[next_x].len()
+                && !visited[next_x][next_y] && terrain[next_x][next_y].is_passable() {
+                visited[next_x][next_y] = true;
+                queue.push_back((next_x, next_y));
+            }
+        }
+    }
+    region
+}
+
+// Checks that the whole floor can be walked from the entrance, and that nothing spawned inside a wall
+pub fn validate(terrain: &[Vec<TileTextureData>], spawns: &[LifeformSpawn]) -> GenerationReport {
+    let mut visited: Vec<Vec<bool>> = terrain.iter().map(|column| vec![false; column.len()]).collect();
+    let entrance = find_tile(terrain, TileTextureData::Entrance);
+    let exit = find_tile(terrain, TileTextureData::Exit);
+
+    if let Some(entrance) = entrance {
+        flood_fill(terrain, entrance, &mut visited);
+    }
+    let exit_reachable = exit.is_some_and(|(x, y)| visited[x][y]);
+
+    let mut unreachable_regions = vec![];
+    for x in 0..terrain.len() {
+        for y in 0..terrain[x].len() {
+            if !visited[x][y] && terrain[x][y].is_passable() {
+                unreachable_regions.push(flood_fill(terrain, (x, y), &mut visited));
+            }
+        }
+    }
+
+    let blocked_spawns = spawns.iter()
+        .filter(|spawn| {
+            !terrain.get(spawn.x)
+                .and_then(|column| column.get(spawn.y))
+                .is_some_and(|tile_data| tile_data.is_passable())
+        })
+        .cloned()
+        .collect();
+
+    GenerationReport {
+        entrance,
+        exit,
+        exit_reachable,
+        unreachable_regions,
+        blocked_spawns,
+        corridors_added: 0,
+        regenerations: 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALL: TileTextureData = TileTextureData::Wall {
+        connects_north: false,
+        connects_south: false,
+        connects_east: false,
+        connects_west: false
+    };
+
+    // Rows are written top to bottom like the ASCII dump, so the first row is the highest y
+    fn terrain(rows: &[&str]) -> Vec<Vec<TileTextureData>> {
+        let height = rows.len();
+        let width = rows[0].len();
+        let mut terrain = vec![vec![TileTextureData::None; height]; width];
+        for (row_idx, row) in rows.iter().enumerate() {
+            for (x, tile) in row.chars().enumerate() {
+                terrain[x][height - 1 - row_idx] = match tile {
+                    '.' => TileTextureData::Floor,
+                    '#' => WALL,
+                    '<' => TileTextureData::Entrance,
+                    '>' => TileTextureData::Exit,
+                    _ => TileTextureData::None
+                };
+            }
+        }
+        terrain
+    }
+
+    fn spawn(x: usize, y: usize) -> LifeformSpawn {
+        LifeformSpawn {
+            tile_data: TileTextureData::Enemy,
+            x,
+            y,
+            health: 50,
+            strength: 5,
+            defense: 0,
+            level: 0,
+            experience: 1
+        }
+    }
+
+    #[test]
+    fn connected_floor_is_valid() {
+        let report = validate(&terrain(&[
+            "#######",
+            "#<...>#",
+            "#######"
+        ]), &[spawn(3, 1)]);
+        assert_eq!(report.entrance, Some((1, 1)));
+        assert_eq!(report.exit, Some((5, 1)));
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn walled_off_rooms_are_reported() {
+        let report = validate(&terrain(&[
+            "###########",
+            "#<.#..#..>#",
+            "###########"
+        ]), &[spawn(3, 1), spawn(20, 20)]);
+        assert!(!report.exit_reachable);
+        assert_eq!(report.unreachable_regions, vec![vec![(4, 1), (5, 1)], vec![(7, 1), (8, 1), (9, 1)]]);
+        assert_eq!(report.blocked_spawns, vec![spawn(3, 1), spawn(20, 20)]);
+        assert!(!report.is_valid());
+    }
+}
@@ -0,0 +1,90 @@
+use crate::tile_data::TileTextureData;
+
+// Walls are laid down unconnected, connect_walls works out which way each one joins its neighbors
+pub(crate) const EMPTY_WALL: TileTextureData = TileTextureData::Wall {
+    connects_north: false,
+    connects_south: false,
+    connects_east: false,
+    connects_west: false
+};
+
+fn check_neighbor(data: &[Vec<TileTextureData>], x: usize, y: usize) -> (bool, bool) {
+    // Return Value (Connect, Create)
+    (data[x][y].connects_to_walls(), data[x][y].makes_walls())
+}
+
+fn check_neighbors(data: &mut [Vec<TileTextureData>], x: usize, y: usize) -> (bool, bool) {
+    let mut connects_north = false;
+    let mut connects_south = false;
+    let mut connects_east = false;
+    let mut connects_west = false;
+    let mut make_wall = false;
+
+    let minus_x = x > 0;
+    let minus_y = y > 0;
+    let plus_x = x + 1 < data.len();
+    let plus_y = y + 1 < data[x].len();
+
+    if minus_x && minus_y {
+        let (_, temp_make_wall) = check_neighbor(data,x - 1, y - 1);
+        make_wall = temp_make_wall || make_wall;
+    }
+    if minus_x {
+        let (inner_connects_west, temp_make_wall) = check_neighbor(data, x - 1, y);
+        make_wall = make_wall || temp_make_wall;
+        connects_west = inner_connects_west;
+    }
+    if minus_x && plus_y {
+        let (_, temp_make_wall) = check_neighbor(data,x - 1, y + 1);
+        make_wall = make_wall || temp_make_wall;
+    }
+    if minus_y {
+        let (inner_connects_south, temp_make_wall) = check_neighbor(data, x, y - 1);
+        make_wall = make_wall || temp_make_wall;
+        connects_south = inner_connects_south;
+    }
+    if plus_y {
+        let (inner_connects_north, temp_make_wall) = check_neighbor(data, x, y + 1);
+        make_wall = make_wall || temp_make_wall;
+        connects_north = inner_connects_north;
+    }
+    if plus_x && minus_y{
+        let (_, temp_make_wall) = check_neighbor(data,x + 1, y - 1);
+        make_wall = make_wall || temp_make_wall;
+    }
+    if plus_x {
+        let (inner_connects_east, temp_make_wall) = check_neighbor(data, x + 1, y);
+        make_wall = make_wall || temp_make_wall;
+        connects_east = inner_connects_east;
+    }
+    if plus_x && plus_y{
+        let (_, temp_make_wall) = check_neighbor(data,x + 1, y + 1);
+        make_wall = make_wall || temp_make_wall;
+    }
+    if make_wall {
+        data[x][y] = TileTextureData::Wall {
+            connects_north,
+            connects_east,
+            connects_west,
+            connects_south
+        };
+        return (connects_west, connects_south);
+    } else {
+        if let TileTextureData::Wall { .. } = data[x][y] {
+            data[x][y] = TileTextureData::None;
+        }
+    }
+    (false, false)
+}
+
+// Shared by every generator, joins walls up to their neighbors and clears away any wall with no floor beside it
+pub(crate) fn connect_walls(output: &mut [Vec<TileTextureData>]) {
+    for x in 0..output.len() {
+        for y in 0..output[x].len() {
+            if let TileTextureData::Wall{..} = output[x][y] {
+                check_neighbors(output, x, y);
+            }
+
+        }
+    }
+}